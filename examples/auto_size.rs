@@ -0,0 +1,78 @@
+//! auto-sizing text input example
+
+use bevy::{color::palettes::css::NAVY, input_focus::InputFocus, prelude::*};
+use bevy_ui_text_input::{
+    TextInputAutoSize, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, mut active_input: ResMut<InputFocus>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let font = TextFont {
+        font: assets.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 25.,
+        ..Default::default()
+    };
+
+    // Grows vertically from one to five lines, then scrolls
+    let multi_line = commands
+        .spawn((
+            TextInputNode {
+                submit_on_enter: true,
+                ..Default::default()
+            },
+            TextInputAutoSize {
+                min_lines: 1,
+                max_lines: Some(5),
+                ..Default::default()
+            },
+            font.clone(),
+            TextInputPrompt::new("Grows as you type.."),
+            Node {
+                width: Val::Px(400.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ))
+        .id();
+
+    // Grows horizontally up to 400 pixels, then scrolls
+    let single_line = commands
+        .spawn((
+            TextInputNode {
+                mode: TextInputMode::SingleLine,
+                ..Default::default()
+            },
+            TextInputAutoSize {
+                min_width: 100.,
+                max_width: Some(400.),
+                ..Default::default()
+            },
+            font,
+            Node::default(),
+            BackgroundColor(NAVY.into()),
+        ))
+        .id();
+
+    active_input.set(multi_line);
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(10.),
+            ..Default::default()
+        })
+        .add_children(&[multi_line, single_line]);
+}
@@ -1,40 +1,345 @@
-//! minimal text input example
+//! text input benchmark
+//!
+//! Spawns a large focused input holding a long document and a grid of idle inputs.
+//! Once a second, logs the frame rate, the average time spent each frame in the UI system sets that hold the text input systems,
+//! the average time spent extracting text for rendering, and the average number of inputs whose `TextInputBuffer` or
+//! `TextInputContents` changed each frame.
+//!
+//! The bench cycles through three phases. The baseline phase recreates the per-frame work done for idle inputs before
+//! cursor blinking moved out of `TextInputBuffer`: every idle input's buffer is mutated and its text is copied into its
+//! `TextInputContents` each frame. The current phase leaves the idle inputs alone. The released phase sets `release_on_blur`
+//! on the idle inputs, so they drop their `TextInputBuffer`s. After each released phase, the average frame costs of the
+//! three phases are logged side by side, along with the heap memory held during the current and released phases.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use bevy::{
     color::palettes::css::NAVY,
     diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
     input_focus::InputFocus,
+    platform::time::Instant,
     prelude::*,
+    render::{ExtractSchedule, RenderApp},
+    ui::UiSystems,
+    ui_render::RenderUiSystems,
     window::{PresentMode, WindowResolution},
 };
-use bevy_ui_text_input::{TextInputNode, TextInputPlugin};
+use bevy_ui_text_input::{
+    TextInputBuffer, TextInputContents, TextInputLargeDocument, TextInputMode, TextInputNode,
+    TextInputPlugin, TextInputQueue,
+    actions::{TextInputAction, TextInputEdit},
+};
+
+/// Number of lines of text in the large input
+const DOCUMENT_LINES: usize = 100_000;
+
+/// Number of small single-line inputs to spawn, to measure the per-frame cost of idle inputs
+const IDLE_INPUTS: usize = 200;
+
+/// Length of each phase of the bench
+const PHASE_DURATION: Duration = Duration::from_secs(5);
+
+/// Bytes currently allocated on the heap
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the bytes allocated on the heap, to compare the memory held by the inputs in each phase
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(Window {
-                    present_mode: PresentMode::AutoNoVsync,
-                    resolution: WindowResolution::new(1920, 1080).with_scale_factor_override(1.0),
-                    ..default()
-                }),
+    let extract_timer = ExtractTimer::default();
+
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                present_mode: PresentMode::AutoNoVsync,
+                resolution: WindowResolution::new(1920, 1080).with_scale_factor_override(1.0),
                 ..default()
             }),
-            TextInputPlugin,
-            FrameTimeDiagnosticsPlugin::default(),
-        ))
-        .add_systems(Startup, setup)
-        .add_systems(Update, fps_system)
-        .run();
+            ..default()
+        }),
+        TextInputPlugin,
+        FrameTimeDiagnosticsPlugin::default(),
+    ))
+    .init_resource::<BenchMeasurements>()
+    .init_resource::<BenchPhase>()
+    .insert_resource(extract_timer.clone())
+    .add_systems(Startup, setup)
+    .add_systems(Update, set_release_on_blur)
+    .add_systems(
+        PostUpdate,
+        (
+            recreate_baseline_work.before(UiSystems::Prepare),
+            start_timer::<PrepareTime>.before(UiSystems::Prepare),
+            end_timer::<PrepareTime>
+                .after(UiSystems::Prepare)
+                .before(UiSystems::Propagate),
+            start_timer::<ContentTime>
+                .after(UiSystems::Propagate)
+                .before(UiSystems::Content),
+            end_timer::<ContentTime>
+                .after(UiSystems::Content)
+                .before(UiSystems::Layout),
+            start_timer::<PostLayoutTime>
+                .after(UiSystems::Layout)
+                .before(UiSystems::PostLayout),
+            end_timer::<PostLayoutTime>.after(UiSystems::PostLayout),
+        ),
+    )
+    .add_systems(Last, (count_changes, report_system).chain());
+
+    app.sub_app_mut(RenderApp)
+        .insert_resource(extract_timer)
+        .add_systems(
+            ExtractSchedule,
+            (
+                start_extract_timer
+                    .after(RenderUiSystems::ExtractTextShadows)
+                    .before(RenderUiSystems::ExtractText),
+                end_extract_timer
+                    .after(RenderUiSystems::ExtractText)
+                    .before(RenderUiSystems::ExtractDebug),
+            ),
+        );
+
+    app.run();
+}
+
+/// Marks the idle inputs
+#[derive(Component)]
+struct IdleInput;
+
+/// Which work is done for the idle inputs each frame
+#[derive(Copy, Clone, PartialEq)]
+enum Phase {
+    /// Every idle input's buffer is changed each frame
+    Baseline,
+    /// The idle inputs are left alone
+    Current,
+    /// The idle inputs have `release_on_blur` set
+    Released,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Baseline => "baseline",
+            Phase::Current => "current",
+            Phase::Released => "released",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Phase::Baseline => Phase::Current,
+            Phase::Current => Phase::Released,
+            Phase::Released => Phase::Baseline,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct BenchPhase {
+    phase: Phase,
+    started: Instant,
+    /// Average milliseconds per frame spent in the timed systems during the last run of each phase
+    averages: [Option<f64>; 3],
+    /// Bytes allocated on the heap at the end of the last run of each phase
+    allocated: [Option<usize>; 3],
+    /// Time spent in the timed systems and frames counted since the phase started
+    time: Duration,
+    frames: u32,
+}
+
+impl Default for BenchPhase {
+    fn default() -> Self {
+        Self {
+            phase: Phase::Baseline,
+            started: Instant::now(),
+            averages: [None; 3],
+            allocated: [None; 3],
+            time: Duration::ZERO,
+            frames: 0,
+        }
+    }
+}
+
+/// Time spent in the UI system set with the same name, since the last report
+#[derive(Resource, Default)]
+struct PrepareTime(SystemsTimer);
+
+#[derive(Resource, Default)]
+struct ContentTime(SystemsTimer);
+
+#[derive(Resource, Default)]
+struct PostLayoutTime(SystemsTimer);
+
+#[derive(Default)]
+struct SystemsTimer {
+    start: Option<Instant>,
+    total: Duration,
+}
+
+trait TimerResource: Resource + Default {
+    fn timer(&mut self) -> &mut SystemsTimer;
+}
+
+impl TimerResource for PrepareTime {
+    fn timer(&mut self) -> &mut SystemsTimer {
+        &mut self.0
+    }
+}
+
+impl TimerResource for ContentTime {
+    fn timer(&mut self) -> &mut SystemsTimer {
+        &mut self.0
+    }
+}
+
+impl TimerResource for PostLayoutTime {
+    fn timer(&mut self) -> &mut SystemsTimer {
+        &mut self.0
+    }
+}
+
+fn start_timer<T: TimerResource>(mut time: ResMut<T>) {
+    time.timer().start = Some(Instant::now());
+}
+
+fn end_timer<T: TimerResource>(mut time: ResMut<T>) {
+    let timer = time.timer();
+    if let Some(start) = timer.start.take() {
+        timer.total += start.elapsed();
+    }
+}
+
+/// Time spent extracting text for rendering, shared between the main world and the render world
+#[derive(Resource, Clone, Default)]
+struct ExtractTimer(Arc<Mutex<SystemsTimer>>);
+
+fn start_extract_timer(timer: Res<ExtractTimer>) {
+    timer.0.lock().unwrap().start = Some(Instant::now());
+}
+
+fn end_extract_timer(timer: Res<ExtractTimer>) {
+    let mut timer = timer.0.lock().unwrap();
+    if let Some(start) = timer.start.take() {
+        timer.total += start.elapsed();
+    }
+}
+
+/// Measurements accumulated since the last report
+#[derive(Resource)]
+struct BenchMeasurements {
+    frames: u32,
+    editors: usize,
+    changed_buffers: usize,
+    changed_contents: usize,
+    last_report: Instant,
+}
+
+impl Default for BenchMeasurements {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            editors: 0,
+            changed_buffers: 0,
+            changed_contents: 0,
+            last_report: Instant::now(),
+        }
+    }
+}
+
+/// During the baseline phase, mutates every idle input's buffer and marks its text as changed,
+/// as ticking the cursor blink of every input used to
+fn recreate_baseline_work(
+    phase: Res<BenchPhase>,
+    mut query: Query<&mut TextInputBuffer, With<IdleInput>>,
+) {
+    if phase.phase != Phase::Baseline {
+        return;
+    }
+
+    for mut buffer in query.iter_mut() {
+        buffer.mark_text_changed();
+    }
+}
+
+/// Sets `release_on_blur` on the idle inputs during the released phase
+fn set_release_on_blur(
+    phase: Res<BenchPhase>,
+    mut query: Query<&mut TextInputNode, With<IdleInput>>,
+) {
+    if !phase.is_changed() {
+        return;
+    }
+
+    let release_on_blur = phase.phase == Phase::Released;
+    for mut input in query.iter_mut() {
+        if input.release_on_blur != release_on_blur {
+            input.release_on_blur = release_on_blur;
+        }
+    }
+}
+
+fn count_changes(
+    mut measurements: ResMut<BenchMeasurements>,
+    editors_query: Query<(), With<TextInputBuffer>>,
+    buffer_query: Query<(), Changed<TextInputBuffer>>,
+    contents_query: Query<(), Changed<TextInputContents>>,
+) {
+    measurements.frames += 1;
+    measurements.editors += editors_query.iter().count();
+    measurements.changed_buffers += buffer_query.iter().count();
+    measurements.changed_contents += contents_query.iter().count();
 }
 
 fn setup(mut commands: Commands, mut active_input: ResMut<InputFocus>) {
     // UI camera
     commands.spawn(Camera2d);
 
+    let document = (0..DOCUMENT_LINES)
+        .map(|i| format!("{i}: The quick brown fox jumps over the lazy dog."))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut queue = TextInputQueue::default();
+    queue.add(TextInputAction::Edit(TextInputEdit::Paste(document)));
+
     let input_entity = commands
         .spawn((
             TextInputNode::default(),
+            TextInputLargeDocument::default(),
+            queue,
             Node {
                 width: Val::Px(500.),
                 height: Val::Px(250.),
@@ -44,6 +349,35 @@ fn setup(mut commands: Commands, mut active_input: ResMut<InputFocus>) {
         ))
         .id();
     active_input.set(input_entity);
+
+    let idle_inputs = commands
+        .spawn(Node {
+            width: Val::Px(1800.),
+            flex_wrap: FlexWrap::Wrap,
+            column_gap: Val::Px(4.),
+            row_gap: Val::Px(4.),
+            ..Default::default()
+        })
+        .with_children(|commands| {
+            for i in 0..IDLE_INPUTS {
+                commands.spawn((
+                    IdleInput,
+                    TextInputNode {
+                        mode: TextInputMode::SingleLine,
+                        ..default()
+                    },
+                    TextInputContents::new(format!("item {i}")),
+                    Node {
+                        width: Val::Px(80.),
+                        height: Val::Px(20.),
+                        ..default()
+                    },
+                    BackgroundColor(NAVY.into()),
+                ));
+            }
+        })
+        .id();
+
     commands
         .spawn(Node {
             width: Val::Percent(100.),
@@ -54,14 +388,71 @@ fn setup(mut commands: Commands, mut active_input: ResMut<InputFocus>) {
             row_gap: Val::Px(10.),
             ..Default::default()
         })
-        .add_child(input_entity);
+        .add_children(&[input_entity, idle_inputs]);
 }
 
-fn fps_system(diagnostics: Res<DiagnosticsStore>) {
-    if let Some(fps) = diagnostics
+fn report_system(
+    diagnostics: Res<DiagnosticsStore>,
+    mut measurements: ResMut<BenchMeasurements>,
+    mut phase: ResMut<BenchPhase>,
+    mut prepare_time: ResMut<PrepareTime>,
+    mut content_time: ResMut<ContentTime>,
+    mut post_layout_time: ResMut<PostLayoutTime>,
+    extract_timer: Res<ExtractTimer>,
+) {
+    if measurements.last_report.elapsed() < Duration::from_secs(1) || measurements.frames == 0 {
+        return;
+    }
+
+    let fps = diagnostics
         .get(&FrameTimeDiagnosticsPlugin::FPS)
-        .map(|fps| fps.smoothed())
-    {
-        info!("fps: {fps:?}");
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let frames = measurements.frames as f64;
+    let per_frame = |time: Duration| time.as_secs_f64() * 1000. / frames;
+    let prepare = std::mem::take(&mut prepare_time.0.total);
+    let content = std::mem::take(&mut content_time.0.total);
+    let post_layout = std::mem::take(&mut post_layout_time.0.total);
+    let extract = std::mem::take(&mut extract_timer.0.lock().unwrap().total);
+    info!(
+        "{}: fps: {fps:.1}, prepare: {:.3}ms/frame, content: {:.3}ms/frame, post layout: {:.3}ms/frame, extract text: {:.3}ms/frame, editors: {:.1}, changed buffers: {:.1}/frame, changed contents: {:.1}/frame",
+        phase.phase.name(),
+        per_frame(prepare),
+        per_frame(content),
+        per_frame(post_layout),
+        per_frame(extract),
+        measurements.editors as f64 / frames,
+        measurements.changed_buffers as f64 / frames,
+        measurements.changed_contents as f64 / frames,
+    );
+
+    phase.time += prepare + content + post_layout + extract;
+    phase.frames += measurements.frames;
+    if PHASE_DURATION <= phase.started.elapsed() {
+        let index = phase.phase as usize;
+        phase.averages[index] = Some(phase.time.as_secs_f64() * 1000. / phase.frames as f64);
+        phase.allocated[index] = Some(ALLOCATED.load(Ordering::Relaxed));
+
+        if phase.phase == Phase::Released
+            && let [Some(baseline), Some(current), Some(released)] = phase.averages
+            && let [_, Some(current_allocated), Some(released_allocated)] = phase.allocated
+        {
+            let mib = |bytes: usize| bytes as f64 / (1024. * 1024.);
+            info!(
+                "{IDLE_INPUTS} idle inputs, baseline: {baseline:.3}ms/frame, current: {current:.3}ms/frame, released: {released:.3}ms/frame, heap current: {:.1}MiB, released: {:.1}MiB ({} bytes less per input)",
+                mib(current_allocated),
+                mib(released_allocated),
+                current_allocated.saturating_sub(released_allocated) / IDLE_INPUTS,
+            );
+        }
+
+        *phase = BenchPhase {
+            phase: phase.phase.next(),
+            averages: phase.averages,
+            allocated: phase.allocated,
+            ..Default::default()
+        };
     }
+
+    *measurements = BenchMeasurements::default();
 }
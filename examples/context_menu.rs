@@ -0,0 +1,69 @@
+//! context menu example
+//!
+//! Right-click the input to open its context menu. The "Insert item link" entry is a custom item.
+
+use bevy::{color::palettes::css::NAVY, input_focus::InputFocus, prelude::*};
+use bevy_ui_text_input::{
+    TextInputNode, TextInputPlugin, TextInputPrompt, TextInputQueue,
+    actions::{TextInputAction, TextInputEdit},
+    context_menu::{TextInputContextMenu, TextInputContextMenuSelected},
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, insert_item_link)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, mut active_input: ResMut<InputFocus>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let input = commands
+        .spawn((
+            TextInputNode::default(),
+            TextInputContextMenu::default().with_custom_item("Insert item link", "item_link"),
+            TextFont {
+                font: assets.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 25.,
+                ..Default::default()
+            },
+            TextInputPrompt::new("Right-click for the context menu.."),
+            Node {
+                width: Val::Px(500.),
+                height: Val::Px(250.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ))
+        .id();
+
+    active_input.set(input);
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        })
+        .add_child(input);
+}
+
+fn insert_item_link(
+    mut selected_reader: MessageReader<TextInputContextMenuSelected>,
+    mut query: Query<&mut TextInputQueue>,
+) {
+    for selected in selected_reader.read() {
+        if selected.name == "item_link"
+            && let Ok(mut queue) = query.get_mut(selected.entity)
+        {
+            queue.add(TextInputAction::Edit(TextInputEdit::Paste(
+                "[Sword of a Thousand Truths]".to_string(),
+            )));
+        }
+    }
+}
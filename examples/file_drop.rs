@@ -0,0 +1,69 @@
+//! file drop example
+//!
+//! Drop files from your file manager onto the inputs.
+//! The path of a file dropped onto the upper input is inserted, the text of a file dropped onto the lower input is inserted.
+
+use bevy::{color::palettes::css::NAVY, prelude::*};
+use bevy_ui_text_input::{
+    FileDropPolicy, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let font = TextFont {
+        font: assets.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 20.,
+        ..Default::default()
+    };
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(20.),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextInputNode {
+                    mode: TextInputMode::SingleLine,
+                    file_drop: FileDropPolicy::Path,
+                    ..Default::default()
+                },
+                font.clone(),
+                TextInputPrompt::new("Drop a file to insert its path.."),
+                Node {
+                    width: Val::Px(600.),
+                    ..default()
+                },
+                BackgroundColor(NAVY.into()),
+            ));
+
+            parent.spawn((
+                TextInputNode {
+                    file_drop: FileDropPolicy::Contents { max_bytes: 64_000 },
+                    ..Default::default()
+                },
+                font,
+                TextInputPrompt::new("Drop a script file to insert its text.."),
+                Node {
+                    width: Val::Px(600.),
+                    height: Val::Px(400.),
+                    ..default()
+                },
+                BackgroundColor(NAVY.into()),
+            ));
+        });
+}
@@ -0,0 +1,87 @@
+//! text input alongside keyboard controlled gameplay
+//!
+//! Move the square with WASD. While the chat input is focused, keys typed into it don't move the square.
+//! Escape is passed through, and unfocuses the input.
+
+use bevy::{color::palettes::css::NAVY, input_focus::InputFocus, prelude::*};
+use bevy_ui_text_input::{
+    TextInputKeyboardConsumption, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
+    text_input_focused,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .init_resource::<TextInputKeyboardConsumption>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (move_player, unfocus_on_escape.run_if(text_input_focused())),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Player;
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+
+    commands.spawn((Player, Sprite::from_color(Color::WHITE, Vec2::splat(50.))));
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::End,
+            padding: UiRect::all(Val::Px(20.)),
+            ..Default::default()
+        })
+        .with_child((
+            TextInputNode {
+                mode: TextInputMode::SingleLine,
+                unfocus_on_click_outside: true,
+                ..Default::default()
+            },
+            TextFont {
+                font: assets.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 25.,
+                ..Default::default()
+            },
+            TextInputPrompt::new("Click to chat.."),
+            Node {
+                width: Val::Px(500.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ));
+}
+
+fn move_player(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut query: Query<&mut Transform, With<Player>>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key_code, step) in [
+        (KeyCode::KeyW, Vec2::Y),
+        (KeyCode::KeyA, Vec2::NEG_X),
+        (KeyCode::KeyS, Vec2::NEG_Y),
+        (KeyCode::KeyD, Vec2::X),
+    ] {
+        if keys.pressed(key_code) {
+            direction += step;
+        }
+    }
+
+    for mut transform in query.iter_mut() {
+        transform.translation += (200. * time.delta_secs() * direction).extend(0.);
+    }
+}
+
+fn unfocus_on_escape(keys: Res<ButtonInput<KeyCode>>, mut input_focus: ResMut<InputFocus>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        input_focus.clear();
+    }
+}
@@ -0,0 +1,110 @@
+//! console log view example
+//!
+//! Lines submitted from the command input, and a line every second, are appended to a read-only log view
+
+use bevy::{color::palettes::css::NAVY, input_focus::InputFocus, prelude::*};
+use bevy_ui_text_input::{
+    SubmitText, TextInputLogView, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
+    TextInputQueue,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (append_submitted_text, append_timer_lines))
+        .run();
+}
+
+#[derive(Component)]
+struct Log;
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, mut active_input: ResMut<InputFocus>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let font = TextFont {
+        font: assets.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 20.,
+        ..Default::default()
+    };
+
+    let log = commands
+        .spawn((
+            Log,
+            TextInputNode {
+                read_only: true,
+                ..Default::default()
+            },
+            TextInputLogView {
+                max_lines: Some(100),
+                ..Default::default()
+            },
+            font.clone(),
+            Node {
+                width: Val::Px(600.),
+                height: Val::Px(300.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ))
+        .id();
+
+    let command_input = commands
+        .spawn((
+            TextInputNode {
+                mode: TextInputMode::SingleLine,
+                unfocus_on_submit: false,
+                ..Default::default()
+            },
+            font,
+            TextInputPrompt::new("Enter a command.."),
+            Node {
+                width: Val::Px(600.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ))
+        .id();
+
+    active_input.set(command_input);
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(10.),
+            ..Default::default()
+        })
+        .add_children(&[log, command_input]);
+}
+
+fn append_submitted_text(
+    mut submit_reader: MessageReader<SubmitText>,
+    mut log_query: Query<&mut TextInputQueue, With<Log>>,
+) {
+    for submit in submit_reader.read() {
+        for mut queue in log_query.iter_mut() {
+            queue.append(format!("> {}", submit.text));
+        }
+    }
+}
+
+fn append_timer_lines(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    mut log_query: Query<&mut TextInputQueue, With<Log>>,
+) {
+    *timer += time.delta_secs();
+    if *timer < 1. {
+        return;
+    }
+    *timer -= 1.;
+
+    for mut queue in log_query.iter_mut() {
+        queue.append(format!("[{:.0}s] tick", time.elapsed_secs()));
+    }
+}
@@ -1,18 +1,24 @@
-//! minimal text input example
+//! multiple text inputs example
+//!
+//! Tab and Shift+Tab move the focus between the inputs
 
 use bevy::{
-    color::palettes::css::{GREY, LIGHT_GOLDENROD_YELLOW},
-    input_focus::InputFocus,
+    color::palettes::css::{DARK_SLATE_GRAY, GREY, LIGHT_GOLDENROD_YELLOW, SKY_BLUE},
+    input_focus::{
+        AutoFocus,
+        tab_navigation::{TabGroup, TabNavigationPlugin},
+    },
     platform::collections::HashMap,
     prelude::*,
 };
 use bevy_ui_text_input::{
-    SubmitText, TextInputFilter, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
+    SubmitText, TextInputColors, TextInputFilter, TextInputFocusRing, TextInputMode, TextInputNode,
+    TextInputPlugin, TextInputPrompt, TextInputStateStyle,
 };
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_plugins((DefaultPlugins, TextInputPlugin, TabNavigationPlugin))
         .add_systems(Startup, setup)
         .add_systems(Update, update)
         .run();
@@ -45,19 +51,23 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
         })
         .with_children(|commands| {
             commands
-                .spawn(Node {
-                    display: Display::Grid,
-                    grid_template_columns: vec![GridTrack::auto(), GridTrack::px(300.)],
-                    column_gap: Val::Px(20.),
-                    row_gap: Val::Px(20.),
-                    ..Default::default()
-                })
+                .spawn((
+                    Node {
+                        display: Display::Grid,
+                        grid_template_columns: vec![GridTrack::auto(), GridTrack::px(300.)],
+                        column_gap: Val::Px(20.),
+                        row_gap: Val::Px(20.),
+                        ..Default::default()
+                    },
+                    TabGroup::default(),
+                ))
                 .with_children(|commands| {
-                    for (filter, prompt) in filters {
+                    for (i, (filter, prompt)) in filters.into_iter().enumerate() {
                         let mut input = commands.spawn((
                             TextInputNode {
                                 mode: TextInputMode::SingleLine,
                                 max_chars: Some(20),
+                                unfocus_on_click_outside: true,
                                 ..Default::default()
                             },
                             TextFont {
@@ -78,10 +88,34 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                                 offset: Val::Px(2.),
                                 color: GREY.into(),
                             },
+                            TextInputStateStyle {
+                                normal: TextInputColors {
+                                    background: Some(Color::BLACK),
+                                    outline: Some(GREY.into()),
+                                    ..Default::default()
+                                },
+                                hovered: TextInputColors {
+                                    background: Some(DARK_SLATE_GRAY.into()),
+                                    ..Default::default()
+                                },
+                                focused: TextInputColors {
+                                    outline: Some(Color::WHITE),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            TextInputFocusRing {
+                                color: SKY_BLUE.into(),
+                                offset: 6.,
+                                ..Default::default()
+                            },
                         ));
                         if let Some(filter) = filter {
                             input.insert(filter);
                         }
+                        if i == 0 {
+                            input.insert(AutoFocus);
+                        }
 
                         let input_entity = input.id();
 
@@ -95,22 +129,10 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
 }
 
 fn update(
-    input_focus: Res<InputFocus>,
     mut events: MessageReader<SubmitText>,
     map: Res<InputMap>,
     mut text_query: Query<&mut Text>,
-    mut outline_query: Query<(Entity, &mut Outline)>,
 ) {
-    if input_focus.is_changed() {
-        for (entity, mut outline) in outline_query.iter_mut() {
-            if input_focus.0.is_some_and(|active| active == entity) {
-                outline.color = Color::WHITE;
-            } else {
-                outline.color = GREY.into();
-            }
-        }
-    }
-
     for event in events.read() {
         let out = map[&event.entity];
         text_query.get_mut(out).unwrap().0 = event.text.clone();
@@ -5,21 +5,25 @@ use bevy::{
     prelude::*,
 };
 use bevy_ui_text_input::{
-    SubmitText, TextInputBuffer, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
-    TextInputQueue, TextInputStyle, actions::TextInputAction,
+    SubmitText, TextInputBuffer, TextInputEditMode, TextInputEditModeChanged, TextInputMode,
+    TextInputNode, TextInputPlugin, TextInputPrompt, TextInputQueue, TextInputStyle,
+    actions::TextInputAction,
 };
 
 fn main() {
     App::new()
         .add_plugins((DefaultPlugins, TextInputPlugin))
         .add_systems(Startup, setup)
-        .add_systems(Update, (button_system, submit))
+        .add_systems(Update, (button_system, submit, edit_mode_indicator))
         .run();
 }
 
 #[derive(Component)]
 struct OutputMarker;
 
+#[derive(Component)]
+struct EditModeMarker;
+
 fn setup(mut commands: Commands, assets: Res<AssetServer>) {
     // UI camera
     commands.spawn(Camera2d);
@@ -389,6 +393,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                 .with_child(Text::new(
                     "Press Shift + Enter or click the button to submit",
                 ));
+            commands.spawn((Text::new("INS"), EditModeMarker));
         });
 }
 
@@ -425,3 +430,18 @@ fn submit(mut events: MessageReader<SubmitText>, mut query: Query<&mut Text, Wit
         }
     }
 }
+
+fn edit_mode_indicator(
+    mut events: MessageReader<TextInputEditModeChanged>,
+    mut query: Query<&mut Text, With<EditModeMarker>>,
+) {
+    for event in events.read() {
+        for mut text in query.iter_mut() {
+            text.0 = match event.mode {
+                TextInputEditMode::Insert => "INS",
+                TextInputEditMode::Overwrite => "OVR",
+            }
+            .to_string();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! text input theme example
+//!
+//! The inputs are styled by `assets/themes/example.text_input_theme.ron`.
+//! Run with `cargo run --example theme --features bevy/file_watcher` to see changes to the theme file applied live.
+
+use bevy::{input_focus::InputFocus, prelude::*};
+use bevy_ui_text_input::{
+    TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt, theme::TextInputThemeHandle,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, mut active_input: ResMut<InputFocus>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let theme = TextInputThemeHandle(assets.load("themes/example.text_input_theme.ron"));
+
+    let inputs: Vec<Entity> = ["First name", "Last name", "Email"]
+        .into_iter()
+        .map(|prompt| {
+            commands
+                .spawn((
+                    TextInputNode {
+                        mode: TextInputMode::SingleLine,
+                        ..Default::default()
+                    },
+                    theme.clone(),
+                    TextInputPrompt::new(prompt),
+                    Node {
+                        width: Val::Px(400.),
+                        ..default()
+                    },
+                    Outline {
+                        width: Val::Px(2.),
+                        offset: Val::Px(2.),
+                        color: Color::NONE,
+                    },
+                ))
+                .id()
+        })
+        .collect();
+
+    active_input.set(inputs[0]);
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(10.),
+            ..Default::default()
+        })
+        .add_children(&inputs);
+}
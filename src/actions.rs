@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+
+use bevy::ecs::entity::Entity;
 use cosmic_text::Action;
 use cosmic_text::BorrowedWithFontSystem;
 use cosmic_text::Edit;
@@ -5,12 +8,20 @@ use cosmic_text::Editor;
 use cosmic_text::Motion;
 use cosmic_text::Selection;
 
+use crate::NewlinePolicy;
 use crate::TextInputFilter;
+use crate::TextInputMode;
+use crate::TextInputNode;
 use crate::clipboard::ClipboardRead;
+use crate::drag_and_drop::cursor_after_deletion;
+use crate::drag_and_drop::is_within_selection;
 use crate::edit::apply_action;
 use crate::edit::apply_motion;
 use crate::edit::buffer_len;
 use crate::edit::cursor_at_line_end;
+use crate::edit::exceeds_line_limits;
+use crate::edit::select_all;
+use crate::text_input_pipeline::DocumentExtents;
 
 /// Actions that can be recieved by a text input
 #[derive(Debug)]
@@ -25,6 +36,17 @@ pub enum TextInputAction {
     Paste,
     /// Paste text from the clipboard
     PasteDeferred(ClipboardRead),
+    /// Insert text dragged from the selection of the `source` input at the specified position, then delete it from the source.
+    /// If the drop is rejected, by the input's filter or limits, the source is left unchanged.
+    MoveFrom {
+        source: Entity,
+        text: String,
+        x: i32,
+        y: i32,
+    },
+    /// Append lines of text to the end of the input, see [`append_lines`](crate::edit::append_lines).
+    /// Appending isn't an edit, it's applied to read-only inputs and can't be undone.
+    Append(String),
     /// A single edit action
     Edit(TextInputEdit),
 }
@@ -36,7 +58,7 @@ pub enum TextInputEdit {
     Motion(Motion, bool),
     /// Escape, clears selection
     Escape,
-    /// Insert character at cursor
+    /// Insert character at cursor, or overwrite the character after the cursor if the bool is true
     Insert(char, bool),
     /// Create new line
     Enter,
@@ -73,19 +95,91 @@ pub enum TextInputEdit {
         lines: i32,
     },
     Paste(String),
+    /// Insert dropped text at the specified position and select it
+    Drop {
+        x: i32,
+        y: i32,
+        text: String,
+    },
+    /// Move the selected text to the specified position, unless the position is inside the selection
+    MoveSelection {
+        x: i32,
+        y: i32,
+    },
     Undo,
     Redo,
     SelectAll,
 }
 
+impl TextInputEdit {
+    /// True for edits that can modify the text, which are rejected by read-only inputs
+    pub fn is_modifying(&self) -> bool {
+        matches!(
+            self,
+            TextInputEdit::Insert(..)
+                | TextInputEdit::Enter
+                | TextInputEdit::Backspace
+                | TextInputEdit::Delete
+                | TextInputEdit::Indent
+                | TextInputEdit::Unindent
+                | TextInputEdit::Paste(_)
+                | TextInputEdit::Drop { .. }
+                | TextInputEdit::MoveSelection { .. }
+                | TextInputEdit::Undo
+                | TextInputEdit::Redo
+        )
+    }
+}
+
+/// Text to be pasted or dropped into the input after applying its newline policy, or `None` if it would exceed `max_chars`
+fn insertable_text<'a>(
+    editor: &BorrowedWithFontSystem<'_, Editor<'static>>,
+    input: &TextInputNode,
+    text: &'a str,
+) -> Option<Cow<'a, str>> {
+    let newline_policy = match (input.mode, input.paste_newlines) {
+        (TextInputMode::SingleLine, NewlinePolicy::Keep) => NewlinePolicy::Collapse,
+        (_, newline_policy) => newline_policy,
+    };
+    newline_policy.apply(text).filter(|text| {
+        input
+            .max_chars
+            .is_none_or(|max| editor.with_buffer(buffer_len) + text.chars().count() <= max)
+    })
+}
+
 /// apply a single `TextInputEdit` to a text editor buffer
+///
+/// Returns true if the edit changed the text
 pub fn apply_text_input_edit(
     edit: TextInputEdit,
     editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
     changes: &mut cosmic_undo_2::Commands<cosmic_text::Change>,
-    max_chars: Option<usize>,
+    input: &TextInputNode,
+    filter_mode: Option<&TextInputFilter>,
+) -> bool {
+    apply_edit(edit, editor, changes, input, filter_mode, None)
+}
+
+/// Applies an edit like [`apply_text_input_edit`], and updates the extents of the lines it changes
+pub(crate) fn apply_edit(
+    edit: TextInputEdit,
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    changes: &mut cosmic_undo_2::Commands<cosmic_text::Change>,
+    input: &TextInputNode,
     filter_mode: Option<&TextInputFilter>,
-) {
+    document_extents: Option<&mut DocumentExtents>,
+) -> bool {
+    if input.read_only && edit.is_modifying() {
+        return false;
+    }
+
+    let max_chars = input.max_chars;
+
+    // Restored if the edit is rejected by the input's limits or filter
+    let cursor = editor.cursor();
+    let selection = editor.selection();
+
     editor.start_change();
 
     match edit {
@@ -96,7 +190,10 @@ pub fn apply_text_input_edit(
             editor.action(Action::Escape);
         }
         TextInputEdit::Insert(ch, overwrite) => {
-            if editor.selection() != Selection::None {
+            // In both modes the character replaces any selected text.
+            // In overwrite mode it otherwise replaces the character after the cursor,
+            // except at the end of a line where it is inserted.
+            if editor.delete_selection() {
                 editor.action(Action::Insert(ch));
             } else if overwrite && !cursor_at_line_end(editor) {
                 editor.action(Action::Delete);
@@ -132,7 +229,7 @@ pub fn apply_text_input_edit(
             editor.action(Action::DoubleClick { x, y });
         }
         TextInputEdit::TripleClick { x, y } => {
-            editor.action(Action::DoubleClick { x, y });
+            editor.action(Action::TripleClick { x, y });
         }
         TextInputEdit::Drag { x, y } => {
             editor.action(Action::Drag { x, y });
@@ -141,8 +238,30 @@ pub fn apply_text_input_edit(
             editor.action(Action::Scroll { lines });
         }
         TextInputEdit::Paste(text) => {
-            if max_chars.is_none_or(|max| editor.with_buffer(buffer_len) + text.len() <= max) {
+            if let Some(text) = insertable_text(editor, input, &text) {
+                editor.insert_string(&text, None);
+            }
+        }
+        TextInputEdit::Drop { x, y, text } => {
+            // The cursor is only moved to the drop position if the text can be inserted
+            if let Some(text) = insertable_text(editor, input, &text) {
+                editor.action(Action::Click { x, y });
+                let start = editor.cursor();
+                editor.insert_string(&text, None);
+                editor.set_selection(Selection::Normal(start));
+            }
+        }
+        TextInputEdit::MoveSelection { x, y } => {
+            if let Some((start, end)) = editor.selection_bounds()
+                && let Some(text) = editor.copy_selection()
+                && let Some(target) = editor.with_buffer(|buffer| buffer.hit(x as f32, y as f32))
+                && !is_within_selection(target, start, end)
+            {
+                editor.delete_selection();
+                let target = cursor_after_deletion(target, start, end);
+                editor.set_cursor(target);
                 editor.insert_string(&text, None);
+                editor.set_selection(Selection::Normal(target));
             }
         }
         TextInputEdit::Undo => {
@@ -158,10 +277,7 @@ pub fn apply_text_input_edit(
             }
         }
         TextInputEdit::SelectAll => {
-            editor.action(Action::Motion(Motion::BufferStart));
-            let cursor = editor.cursor();
-            editor.set_selection(Selection::Normal(cursor));
-            editor.action(Action::Motion(Motion::BufferEnd));
+            select_all(editor);
         }
         TextInputEdit::Enter => {
             editor.action(Action::Enter);
@@ -169,11 +285,19 @@ pub fn apply_text_input_edit(
     }
 
     let Some(mut change) = editor.finish_change() else {
-        return;
+        return false;
     };
 
     if change.items.is_empty() {
-        return;
+        return false;
+    }
+
+    if exceeds_line_limits(editor, input) {
+        change.reverse();
+        editor.apply_change(&change);
+        editor.set_cursor(cursor);
+        editor.set_selection(selection);
+        return false;
     }
 
     if let Some(filter_mode) = filter_mode {
@@ -181,10 +305,240 @@ pub fn apply_text_input_edit(
         if !filter_mode.is_match(&text) {
             change.reverse();
             editor.apply_change(&change);
-            return;
+            editor.set_cursor(cursor);
+            editor.set_selection(selection);
+            return false;
         }
     }
 
+    if let Some(document_extents) = document_extents {
+        document_extents.apply_change(&change);
+    }
     changes.push(change);
     editor.set_redraw(true);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_text::Attrs;
+    use cosmic_text::Buffer;
+    use cosmic_text::Family;
+    use cosmic_text::FontSystem;
+    use cosmic_text::Metrics;
+    use cosmic_text::Shaping;
+    use cosmic_text::Wrap;
+    use cosmic_text::fontdb;
+
+    fn font_system() -> FontSystem {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(include_bytes!("../assets/fonts/FiraMono-Medium.ttf").to_vec());
+        FontSystem::new_with_locale_and_db("en-US".to_string(), db)
+    }
+
+    /// Editor holding `text` with the cursor at its end, wrapping lines at `width` pixels of 10px monospace glyphs
+    fn editor(font_system: &mut FontSystem, text: &str, width: f32) -> Editor<'static> {
+        let mut buffer = Buffer::new(font_system, Metrics::new(10., 10.));
+        buffer.set_size(font_system, Some(width), None);
+        buffer.set_wrap(font_system, Wrap::Glyph);
+        buffer.set_text(
+            font_system,
+            text,
+            &Attrs::new().family(Family::Name("Fira Mono")),
+            Shaping::Advanced,
+        );
+        let mut editor = Editor::new(buffer);
+        editor
+            .borrow_with(font_system)
+            .action(Action::Motion(Motion::BufferEnd));
+        editor
+    }
+
+    fn apply(
+        font_system: &mut FontSystem,
+        editor: &mut Editor<'static>,
+        input: &TextInputNode,
+        edit: TextInputEdit,
+    ) -> bool {
+        let mut changes = cosmic_undo_2::Commands::default();
+        apply_text_input_edit(
+            edit,
+            &mut editor.borrow_with(font_system),
+            &mut changes,
+            input,
+            None,
+        )
+    }
+
+    fn text(editor: &Editor<'static>) -> String {
+        editor.with_buffer(crate::get_text)
+    }
+
+    #[test]
+    fn rejected_drops_leave_the_cursor_and_selection_unchanged() {
+        let mut font_system = font_system();
+        let mut editor = editor(&mut font_system, "12345", 1000.);
+        editor.set_cursor(cosmic_text::Cursor::new(0, 1));
+        editor.set_selection(Selection::Normal(cosmic_text::Cursor::new(0, 3)));
+        let input = TextInputNode {
+            max_chars: Some(6),
+            ..Default::default()
+        };
+        let drop = || TextInputEdit::Drop {
+            x: 0,
+            y: 0,
+            text: "67".to_string(),
+        };
+
+        // Rejected by max_chars
+        assert!(!apply(&mut font_system, &mut editor, &input, drop()));
+
+        // Rejected by the filter
+        let input = TextInputNode::default();
+        let mut changes = cosmic_undo_2::Commands::default();
+        let filter = TextInputFilter::Alphanumeric;
+        let rejected = TextInputEdit::Drop {
+            x: 0,
+            y: 0,
+            text: "6 7".to_string(),
+        };
+        assert!(!apply_text_input_edit(
+            rejected,
+            &mut editor.borrow_with(&mut font_system),
+            &mut changes,
+            &input,
+            Some(&filter),
+        ));
+
+        assert_eq!(text(&editor), "12345");
+        assert_eq!(editor.cursor(), cosmic_text::Cursor::new(0, 1));
+        assert_eq!(
+            editor.selection(),
+            Selection::Normal(cosmic_text::Cursor::new(0, 3))
+        );
+    }
+
+    #[test]
+    fn max_chars_counts_chars_not_bytes() {
+        let mut font_system = font_system();
+        let mut editor = editor(&mut font_system, "", 1000.);
+        let input = TextInputNode {
+            max_chars: Some(3),
+            ..Default::default()
+        };
+
+        assert!(apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("äöü".to_string())
+        ));
+        assert_eq!(text(&editor), "äöü");
+
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("ß".to_string())
+        ));
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Insert('x', false)
+        ));
+        assert_eq!(text(&editor), "äöü");
+    }
+
+    #[test]
+    fn max_lines_rejects_new_lines() {
+        let mut font_system = font_system();
+        let mut editor = editor(&mut font_system, "one\ntwo", 1000.);
+        let input = TextInputNode {
+            max_lines: Some(2),
+            ..Default::default()
+        };
+
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Enter
+        ));
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("\nthree".to_string())
+        ));
+        assert!(apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste(" three".to_string())
+        ));
+        assert_eq!(text(&editor), "one\ntwo three");
+    }
+
+    #[test]
+    fn max_visual_lines_rejects_wrapping_text() {
+        let mut font_system = font_system();
+        // Wide enough for a few glyphs per line
+        let mut editor = editor(&mut font_system, "abc", 35.);
+        let input = TextInputNode {
+            max_visual_lines: Some(2),
+            ..Default::default()
+        };
+
+        assert!(apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("def".to_string())
+        ));
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("ghijklmnopqrstuvwxyz".to_string())
+        ));
+        assert_eq!(text(&editor), "abcdef");
+    }
+
+    #[test]
+    fn single_line_inputs_collapse_pasted_newlines() {
+        let mut font_system = font_system();
+        let mut editor = editor(&mut font_system, "", 1000.);
+        let input = TextInputNode {
+            mode: TextInputMode::SingleLine,
+            ..Default::default()
+        };
+
+        assert!(apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("one\n\ntwo".to_string())
+        ));
+        assert_eq!(text(&editor), "one two");
+    }
+
+    #[test]
+    fn reject_newline_policy_rejects_pasted_newlines() {
+        let mut font_system = font_system();
+        let mut editor = editor(&mut font_system, "", 1000.);
+        let input = TextInputNode {
+            paste_newlines: NewlinePolicy::Reject,
+            ..Default::default()
+        };
+
+        assert!(!apply(
+            &mut font_system,
+            &mut editor,
+            &input,
+            TextInputEdit::Paste("one\ntwo".to_string())
+        ));
+        assert_eq!(text(&editor), "");
+    }
 }
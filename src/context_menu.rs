@@ -0,0 +1,376 @@
+use crate::TextInputBuffer;
+use crate::TextInputNode;
+use crate::TextInputQueue;
+use crate::actions::TextInputAction;
+use crate::actions::TextInputEdit;
+use crate::edit::is_buffer_empty;
+use bevy::color::Color;
+use bevy::color::palettes::css::SKY_BLUE;
+use bevy::color::palettes::tailwind::GRAY_500;
+use bevy::color::palettes::tailwind::GRAY_800;
+use bevy::ecs::children;
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::hierarchy::ChildOf;
+use bevy::ecs::message::Message;
+use bevy::ecs::message::MessageReader;
+use bevy::ecs::message::MessageWriter;
+use bevy::ecs::observer::On;
+use bevy::ecs::query::Changed;
+use bevy::ecs::query::With;
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::ecs::spawn::SpawnRelated;
+use bevy::ecs::system::Commands;
+use bevy::ecs::system::Query;
+use bevy::ecs::system::Res;
+use bevy::ecs::system::ResMut;
+use bevy::input::keyboard::KeyCode;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input_focus::InputFocus;
+use bevy::picking::events::Click;
+use bevy::picking::events::Pointer;
+use bevy::picking::events::Press;
+use bevy::picking::pointer::PointerButton;
+use bevy::reflect::Reflect;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::text::TextColor;
+use bevy::text::TextFont;
+use bevy::ui::BackgroundColor;
+use bevy::ui::ComputedUiTargetCamera;
+use bevy::ui::FlexDirection;
+use bevy::ui::GlobalZIndex;
+use bevy::ui::Interaction;
+use bevy::ui::Node;
+use bevy::ui::PositionType;
+use bevy::ui::UiRect;
+use bevy::ui::UiScale;
+use bevy::ui::UiTargetCamera;
+use bevy::ui::Val;
+use bevy::ui::widget::Button;
+use bevy::ui::widget::Text;
+use cosmic_text::Edit;
+
+/// Right-clicking an input with a `TextInputContextMenu` opens a menu of its `items` at the pointer.
+/// Items that can't be applied, such as Copy without a selection or Paste into a read-only input, are disabled.
+/// Optional component.
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputContextMenu {
+    /// The menu's items, from top to bottom
+    pub items: Vec<TextInputContextMenuItem>,
+    /// Font size of the items' labels
+    pub font_size: f32,
+    /// Color of the labels of enabled items
+    pub text_color: Color,
+    /// Color of the labels of disabled items
+    pub disabled_text_color: Color,
+    /// Background color of the menu
+    pub background_color: Color,
+    /// Background color of the hovered item
+    pub hovered_color: Color,
+}
+
+impl Default for TextInputContextMenu {
+    fn default() -> Self {
+        Self {
+            items: vec![
+                TextInputContextMenuItem::new("Undo", TextInputContextMenuAction::Undo),
+                TextInputContextMenuItem::new("Redo", TextInputContextMenuAction::Redo),
+                TextInputContextMenuItem::new("Cut", TextInputContextMenuAction::Cut),
+                TextInputContextMenuItem::new("Copy", TextInputContextMenuAction::Copy),
+                TextInputContextMenuItem::new("Paste", TextInputContextMenuAction::Paste),
+                TextInputContextMenuItem::new("Delete", TextInputContextMenuAction::Delete),
+                TextInputContextMenuItem::new("Select All", TextInputContextMenuAction::SelectAll),
+            ],
+            font_size: 16.,
+            text_color: Color::WHITE,
+            disabled_text_color: GRAY_500.into(),
+            background_color: GRAY_800.into(),
+            hovered_color: SKY_BLUE.into(),
+        }
+    }
+}
+
+impl TextInputContextMenu {
+    /// Append a custom item, which sends a [`TextInputContextMenuSelected`] message with `name` when chosen
+    pub fn with_custom_item(mut self, label: impl Into<String>, name: impl Into<String>) -> Self {
+        self.items.push(TextInputContextMenuItem::new(
+            label,
+            TextInputContextMenuAction::Custom(name.into()),
+        ));
+        self
+    }
+}
+
+/// An item in a [`TextInputContextMenu`]
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct TextInputContextMenuItem {
+    /// The item's label
+    pub label: String,
+    /// The action performed when the item is chosen
+    pub action: TextInputContextMenuAction,
+}
+
+impl TextInputContextMenuItem {
+    pub fn new(label: impl Into<String>, action: TextInputContextMenuAction) -> Self {
+        Self {
+            label: label.into(),
+            action,
+        }
+    }
+}
+
+/// The action performed by a [`TextInputContextMenuItem`]
+#[derive(Clone, Debug, PartialEq, Eq, Reflect)]
+pub enum TextInputContextMenuAction {
+    Cut,
+    Copy,
+    Paste,
+    Delete,
+    SelectAll,
+    Undo,
+    Redo,
+    /// Sends a [`TextInputContextMenuSelected`] message with the name, always enabled
+    Custom(String),
+}
+
+impl TextInputContextMenuAction {
+    /// Returns true if the action can be applied to the input
+    pub fn is_enabled(&self, input: &TextInputNode, buffer: &TextInputBuffer) -> bool {
+        let has_selection = buffer
+            .editor
+            .selection_bounds()
+            .is_some_and(|(start, end)| start != end);
+        let editable = input.is_enabled && !input.read_only;
+        match self {
+            Self::Cut | Self::Delete => editable && has_selection,
+            Self::Copy => has_selection,
+            Self::Paste => editable,
+            Self::SelectAll => !buffer.editor.with_buffer(is_buffer_empty),
+            Self::Undo => editable && buffer.can_undo(),
+            Self::Redo => editable && buffer.can_redo(),
+            Self::Custom(_) => true,
+        }
+    }
+
+    /// The action to queue on the input, `None` for custom actions
+    pub fn text_input_action(&self) -> Option<TextInputAction> {
+        Some(match self {
+            Self::Cut => TextInputAction::Cut,
+            Self::Copy => TextInputAction::Copy,
+            Self::Paste => TextInputAction::Paste,
+            Self::Delete => TextInputAction::Edit(TextInputEdit::Delete),
+            Self::SelectAll => TextInputAction::Edit(TextInputEdit::SelectAll),
+            Self::Undo => TextInputAction::Edit(TextInputEdit::Undo),
+            Self::Redo => TextInputAction::Edit(TextInputEdit::Redo),
+            Self::Custom(_) => return None,
+        })
+    }
+}
+
+/// Sent when a custom context menu item is chosen
+#[derive(Message, Clone, Debug)]
+pub struct TextInputContextMenuSelected {
+    /// The text input entity the menu was opened for
+    pub entity: Entity,
+    /// The name of the custom item
+    pub name: String,
+}
+
+/// The root node of an open context menu
+#[derive(Component, Copy, Clone, Debug)]
+pub struct TextInputContextMenuPanel {
+    /// The text input entity the menu was opened for
+    pub input: Entity,
+}
+
+/// An item node in an open context menu
+#[derive(Component, Clone, Debug)]
+pub struct TextInputContextMenuEntry {
+    /// The text input entity the menu was opened for
+    pub input: Entity,
+    /// The action performed when the item is chosen
+    pub action: TextInputContextMenuAction,
+    /// Disabled items can't be chosen
+    pub enabled: bool,
+}
+
+/// Closes open context menus when the pointer is pressed outside of them,
+/// and opens a context menu when an input with a `TextInputContextMenu` is right-clicked.
+pub fn on_press_text_input_context_menu(
+    press: On<Pointer<Press>>,
+    mut commands: Commands,
+    mut input_focus: ResMut<InputFocus>,
+    ui_scale: Res<UiScale>,
+    panel_query: Query<Entity, With<TextInputContextMenuPanel>>,
+    parent_query: Query<&ChildOf>,
+    input_query: Query<(
+        &TextInputNode,
+        &TextInputBuffer,
+        &TextInputContextMenu,
+        &ComputedUiTargetCamera,
+    )>,
+) {
+    // Only handle the press once, before it propagates
+    if press.entity != press.original_event_target() {
+        return;
+    }
+
+    let target = press.entity;
+    if is_in_context_menu(target, &panel_query, &parent_query) {
+        return;
+    }
+
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn();
+    }
+
+    if press.button != PointerButton::Secondary {
+        return;
+    }
+
+    let Ok((input, buffer, menu, target_camera)) = input_query.get(target) else {
+        return;
+    };
+
+    if input.is_enabled && input_focus.get() != Some(target) {
+        input_focus.set(target);
+    }
+
+    // The pointer's position is in logical window pixels, UI values are also scaled by `UiScale`
+    let position = press.pointer_location.position / ui_scale.0;
+    let mut panel = commands.spawn((
+        TextInputContextMenuPanel { input: target },
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(position.x),
+            top: Val::Px(position.y),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::vertical(Val::Px(4.)),
+            ..Default::default()
+        },
+        BackgroundColor(menu.background_color),
+        GlobalZIndex(i32::MAX),
+    ));
+    if let Some(camera) = target_camera.get() {
+        panel.insert(UiTargetCamera(camera));
+    }
+
+    panel.with_children(|panel| {
+        for item in menu.items.iter() {
+            let enabled = item.action.is_enabled(input, buffer);
+            panel.spawn((
+                TextInputContextMenuEntry {
+                    input: target,
+                    action: item.action.clone(),
+                    enabled,
+                },
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(12.), Val::Px(4.)),
+                    ..Default::default()
+                },
+                BackgroundColor(Color::NONE),
+                children![(
+                    Text::new(item.label.clone()),
+                    TextFont::from_font_size(menu.font_size),
+                    TextColor(if enabled {
+                        menu.text_color
+                    } else {
+                        menu.disabled_text_color
+                    }),
+                )],
+            ));
+        }
+    });
+}
+
+/// Applies the action of a clicked context menu item and closes the menu
+pub fn on_click_text_input_context_menu(
+    mut click: On<Pointer<Click>>,
+    mut commands: Commands,
+    mut input_focus: ResMut<InputFocus>,
+    entry_query: Query<&TextInputContextMenuEntry>,
+    panel_query: Query<Entity, With<TextInputContextMenuPanel>>,
+    mut queue_query: Query<&mut TextInputQueue>,
+    mut selected_writer: MessageWriter<TextInputContextMenuSelected>,
+) {
+    let Ok(entry) = entry_query.get(click.entity) else {
+        return;
+    };
+
+    click.propagate(false);
+
+    if click.button != PointerButton::Primary || !entry.enabled {
+        return;
+    }
+
+    if let Some(action) = entry.action.text_input_action() {
+        if let Ok(mut queue) = queue_query.get_mut(entry.input) {
+            queue.add(action);
+        }
+    } else if let TextInputContextMenuAction::Custom(name) = &entry.action {
+        selected_writer.write(TextInputContextMenuSelected {
+            entity: entry.input,
+            name: name.clone(),
+        });
+    }
+
+    // Pressing the menu may have moved the focus away from the input
+    if input_focus.get() != Some(entry.input) {
+        input_focus.set(entry.input);
+    }
+
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn();
+    }
+}
+
+/// Highlights hovered context menu items, and closes context menus on Escape or when their input is despawned
+pub fn text_input_context_menu_system(
+    mut commands: Commands,
+    mut keyboard_inputs: MessageReader<KeyboardInput>,
+    panel_query: Query<(Entity, &TextInputContextMenuPanel)>,
+    menu_query: Query<&TextInputContextMenu>,
+    mut entry_query: Query<
+        (
+            &TextInputContextMenuEntry,
+            &Interaction,
+            &mut BackgroundColor,
+        ),
+        Changed<Interaction>,
+    >,
+) {
+    let escape_pressed = keyboard_inputs.read().any(|keyboard_input| {
+        keyboard_input.key_code == KeyCode::Escape && keyboard_input.state.is_pressed()
+    });
+
+    for (entity, panel) in panel_query.iter() {
+        if escape_pressed || !menu_query.contains(panel.input) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (entry, interaction, mut background_color) in entry_query.iter_mut() {
+        let Ok(menu) = menu_query.get(entry.input) else {
+            continue;
+        };
+        background_color.0 = if entry.enabled && *interaction != Interaction::None {
+            menu.hovered_color
+        } else {
+            Color::NONE
+        };
+    }
+}
+
+/// Returns true if the entity is part of an open context menu
+pub fn is_in_context_menu(
+    entity: Entity,
+    panel_query: &Query<Entity, With<TextInputContextMenuPanel>>,
+    parent_query: &Query<&ChildOf>,
+) -> bool {
+    panel_query.contains(entity)
+        || parent_query
+            .iter_ancestors(entity)
+            .any(|ancestor| panel_query.contains(ancestor))
+}
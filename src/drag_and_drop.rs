@@ -0,0 +1,522 @@
+use crate::FileDropPolicy;
+use crate::TextInputBuffer;
+use crate::TextInputGlobalState;
+use crate::TextInputNode;
+use crate::TextInputQueue;
+use crate::actions::TextInputAction;
+use crate::actions::TextInputEdit;
+use crate::text_input_pipeline::TextInputPipeline;
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::message::MessageReader;
+use bevy::ecs::observer::On;
+use bevy::ecs::query::Has;
+use bevy::ecs::query::With;
+use bevy::ecs::system::Commands;
+use bevy::ecs::system::Local;
+use bevy::ecs::system::Query;
+use bevy::ecs::system::Res;
+use bevy::ecs::system::ResMut;
+use bevy::input_focus::InputFocus;
+use bevy::log::warn;
+use bevy::math::Rect;
+use bevy::math::Vec2;
+use bevy::picking::events::Click;
+use bevy::picking::events::DragDrop;
+use bevy::picking::events::DragLeave;
+use bevy::picking::events::DragOver;
+use bevy::picking::events::Pointer;
+use bevy::picking::hover::HoverMap;
+use bevy::picking::pointer::PointerButton;
+use bevy::picking::pointer::PointerId;
+use bevy::picking::pointer::PointerLocation;
+use bevy::tasks::IoTaskPool;
+use bevy::tasks::Task;
+use bevy::tasks::futures::check_ready;
+use bevy::ui::ComputedNode;
+use bevy::ui::UiGlobalTransform;
+use bevy::window::FileDragAndDrop;
+use cosmic_text::Action;
+use cosmic_text::Buffer;
+use cosmic_text::Cursor;
+use cosmic_text::Edit;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Inserted on the focused input when the pointer is pressed inside its selection, which can then be dragged and dropped
+#[derive(Component)]
+pub struct SelectionDragData {
+    /// Set once the pointer moves. Until then, releasing the button places the cursor as a click would.
+    pub(crate) dragging: bool,
+}
+
+/// Where text dragged over an input will be dropped, drawn as a bar cursor.
+/// Inserted on inputs while a selection is dragged over them.
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub struct TextInputDropCaret {
+    /// Position of the top left of the drop caret in physical pixels relative to the text
+    pub(crate) position: Vec2,
+}
+
+/// Inputs that text can be dropped into
+pub(crate) fn accepts_drop(input: &TextInputNode) -> bool {
+    input.is_enabled && !input.read_only && input.allow_drag_and_drop
+}
+
+/// Position of the pointer relative to the input's text, in physical pixels
+pub(crate) fn pointer_to_text_position(
+    node: &ComputedNode,
+    transform: &UiGlobalTransform,
+    buffer: &Buffer,
+    pointer_position: Vec2,
+) -> Vec2 {
+    let rect = Rect::from_center_size(transform.translation, node.size());
+    pointer_position * node.inverse_scale_factor().recip() - rect.min
+        + Vec2::new(buffer.scroll().horizontal, 0.)
+}
+
+/// True if the cursor is between the start and end of a selection, inclusive
+pub(crate) fn is_within_selection(cursor: Cursor, start: Cursor, end: Cursor) -> bool {
+    (start.line, start.index) <= (cursor.line, cursor.index)
+        && (cursor.line, cursor.index) <= (end.line, end.index)
+}
+
+/// Position of a cursor outside of the text from `start` to `end`, after that text is deleted.
+/// Cursors after the deleted text are shifted back by its deletion.
+pub(crate) fn cursor_after_deletion(cursor: Cursor, start: Cursor, end: Cursor) -> Cursor {
+    if (cursor.line, cursor.index) < (end.line, end.index) {
+        cursor
+    } else if cursor.line == end.line {
+        Cursor::new(start.line, start.index + cursor.index - end.index)
+    } else {
+        Cursor::new(cursor.line - (end.line - start.line), cursor.index)
+    }
+}
+
+/// Position of the top left of a cursor relative to the text, if it's on a visible line
+pub(crate) fn cursor_position(buffer: &Buffer, cursor: Cursor) -> Option<Vec2> {
+    buffer
+        .layout_runs()
+        .filter(|run| run.line_i == cursor.line)
+        .find_map(|run| {
+            let x = if let Some(glyph) = run
+                .glyphs
+                .iter()
+                .find(|glyph| glyph.start <= cursor.index && cursor.index < glyph.end)
+            {
+                if glyph.level.is_rtl() {
+                    glyph.x + glyph.w
+                } else {
+                    glyph.x
+                }
+            } else {
+                match run.glyphs.last() {
+                    Some(glyph) if cursor.index == glyph.end => {
+                        if glyph.level.is_rtl() {
+                            glyph.x
+                        } else {
+                            glyph.x + glyph.w
+                        }
+                    }
+                    Some(_) => return None,
+                    None => 0.,
+                }
+            };
+            Some(Vec2::new(x, run.line_top))
+        })
+}
+
+/// The drop caret of an input at a position relative to its text.
+/// The drop caret isn't shown over the dragged selection itself, where dropping does nothing.
+pub(crate) fn drop_caret_at(
+    buffer: &Buffer,
+    position: Vec2,
+    dragged_selection: Option<(Cursor, Cursor)>,
+) -> Option<TextInputDropCaret> {
+    let cursor = buffer.hit(position.x, position.y)?;
+    if dragged_selection.is_some_and(|(start, end)| is_within_selection(cursor, start, end)) {
+        return None;
+    }
+    cursor_position(buffer, cursor).map(|position| TextInputDropCaret { position })
+}
+
+pub(crate) fn on_drag_over_text_input(
+    trigger: On<Pointer<DragOver>>,
+    drag_query: Query<(), With<SelectionDragData>>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &mut TextInputBuffer,
+        &TextInputNode,
+    )>,
+    mut text_input_pipeline: ResMut<TextInputPipeline>,
+    mut commands: Commands,
+) {
+    if trigger.button != PointerButton::Primary || !drag_query.contains(trigger.dragged) {
+        return;
+    }
+
+    let Ok((node, transform, mut buffer, input)) = node_query.get_mut(trigger.entity) else {
+        return;
+    };
+
+    if !accepts_drop(input) {
+        return;
+    }
+
+    let mut editor = buffer
+        .editor
+        .borrow_with(&mut text_input_pipeline.font_system);
+
+    // The input's lines may have been released while it was unfocused, and need to be shaped before hit testing.
+    editor.shape_as_needed(false);
+
+    let drop_caret = editor.with_buffer(|buffer| {
+        let position =
+            pointer_to_text_position(node, transform, buffer, trigger.pointer_location.position);
+        drop_caret_at(buffer, position, None)
+    });
+
+    match drop_caret {
+        Some(drop_caret) => {
+            commands.entity(trigger.entity).insert(drop_caret);
+        }
+        None => {
+            commands
+                .entity(trigger.entity)
+                .try_remove::<TextInputDropCaret>();
+        }
+    }
+}
+
+pub(crate) fn on_drag_leave_text_input(trigger: On<Pointer<DragLeave>>, mut commands: Commands) {
+    commands
+        .entity(trigger.entity)
+        .try_remove::<TextInputDropCaret>();
+}
+
+/// Drops a selection dragged from another input. The text is moved, or copied if Ctrl or Command is held or the source input is read-only.
+/// Moved text is deleted from the source input after it has been inserted, so if the drop is rejected by the input's filter or limits, the source is left unchanged.
+pub(crate) fn on_drag_drop_text_input(
+    trigger: On<Pointer<DragDrop>>,
+    node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &TextInputBuffer,
+        &TextInputNode,
+        Has<SelectionDragData>,
+    )>,
+    mut queue_query: Query<&mut TextInputQueue>,
+    global_state: Res<TextInputGlobalState>,
+    mut input_focus: ResMut<InputFocus>,
+    mut commands: Commands,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    commands
+        .entity(trigger.entity)
+        .try_remove::<TextInputDropCaret>();
+
+    let Ok((_, _, source_buffer, source_input, true)) = node_query.get(trigger.dropped) else {
+        return;
+    };
+
+    let Some(text) = source_buffer.editor.copy_selection() else {
+        return;
+    };
+
+    let Ok((node, transform, buffer, input, _)) = node_query.get(trigger.entity) else {
+        return;
+    };
+
+    if !accepts_drop(input) {
+        return;
+    }
+
+    let Ok(mut queue) = queue_query.get_mut(trigger.entity) else {
+        return;
+    };
+
+    let position = buffer.editor.with_buffer(|buffer| {
+        pointer_to_text_position(node, transform, buffer, trigger.pointer_location.position)
+    });
+    let (x, y) = (position.x as i32, position.y as i32);
+
+    if global_state.command || source_input.read_only {
+        queue.add(TextInputAction::Edit(TextInputEdit::Drop { x, y, text }));
+    } else {
+        queue.add(TextInputAction::MoveFrom {
+            source: trigger.dropped,
+            text,
+            x,
+            y,
+        });
+    }
+
+    input_focus.set(trigger.entity);
+}
+
+/// Releasing the pointer inside the selection without dragging places the cursor, like a normal click
+pub(crate) fn on_click_text_input_selection(
+    trigger: On<Pointer<Click>>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &mut TextInputBuffer,
+        &SelectionDragData,
+    )>,
+    mut text_input_pipeline: ResMut<TextInputPipeline>,
+    mut commands: Commands,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((node, transform, mut buffer, drag_data)) = node_query.get_mut(trigger.entity) else {
+        return;
+    };
+
+    // After a drag the text is dropped in `on_drag_end_text_input` instead
+    if drag_data.dragging {
+        return;
+    }
+
+    commands
+        .entity(trigger.entity)
+        .try_remove::<SelectionDragData>();
+
+    let mut editor = buffer
+        .editor
+        .borrow_with(&mut text_input_pipeline.font_system);
+
+    let position = editor.with_buffer(|buffer| {
+        pointer_to_text_position(node, transform, buffer, trigger.pointer_location.position)
+    });
+
+    editor.action(Action::Click {
+        x: position.x as i32,
+        y: position.y as i32,
+    });
+}
+
+/// Reads the text of a dropped file, if it's no larger than `max_bytes`
+fn read_dropped_file(path: &Path, max_bytes: u64) -> io::Result<String> {
+    let len = std::fs::metadata(path)?.len();
+    if max_bytes < len {
+        return Err(io::Error::other(format!(
+            "the file is {len} bytes, larger than the limit of {max_bytes} bytes"
+        )));
+    }
+    std::fs::read_to_string(path)
+}
+
+/// A read of the contents of files dropped onto a text input, running on the [`IoTaskPool`]
+pub struct PendingFileRead {
+    entity: Entity,
+    pointer_position: Vec2,
+    task: Task<Vec<(PathBuf, io::Result<String>)>>,
+}
+
+/// Inserts files dragged from the operating system and dropped onto the window into the text input under the mouse pointer,
+/// according to the input's `file_drop` policy. The text is dropped at the pointer and is subject to the input's filter and `max_chars`.
+/// The paths or contents of several files dropped together are separated by newlines.
+///
+/// With [`FileDropPolicy::Contents`] the files are read on the [`IoTaskPool`] and their text is inserted once the reads complete,
+/// so it may arrive a few frames after the drop.
+pub fn text_input_file_drop_system(
+    mut file_drag_and_drop_reader: MessageReader<FileDragAndDrop>,
+    hover_map: Res<HoverMap>,
+    pointer_query: Query<(&PointerId, &PointerLocation)>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &TextInputBuffer,
+        &TextInputNode,
+        &mut TextInputQueue,
+    )>,
+    mut input_focus: ResMut<InputFocus>,
+    mut pending_reads: Local<Vec<PendingFileRead>>,
+) {
+    let mut drops: Vec<(Entity, Vec2, Vec<PathBuf>)> = vec![];
+
+    for file_drag_and_drop in file_drag_and_drop_reader.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = file_drag_and_drop else {
+            continue;
+        };
+
+        let Some(pointer_position) = pointer_query
+            .iter()
+            .find(|(pointer_id, _)| pointer_id.is_mouse())
+            .and_then(|(_, pointer_location)| pointer_location.location())
+            .map(|location| location.position)
+        else {
+            continue;
+        };
+
+        let Some(entity) = hover_map.get(&PointerId::Mouse).and_then(|hovered| {
+            hovered
+                .keys()
+                .copied()
+                .find(|entity| node_query.contains(*entity))
+        }) else {
+            continue;
+        };
+
+        let Ok((_, _, _, input, _)) = node_query.get(entity) else {
+            continue;
+        };
+
+        if !input.is_enabled || input.read_only || input.file_drop == FileDropPolicy::Ignore {
+            continue;
+        }
+
+        match drops
+            .iter_mut()
+            .find(|(drop_entity, ..)| *drop_entity == entity)
+        {
+            Some((_, _, paths)) => paths.push(path_buf.clone()),
+            None => drops.push((entity, pointer_position, vec![path_buf.clone()])),
+        }
+    }
+
+    let mut texts: Vec<(Entity, Vec2, String)> = vec![];
+
+    for (entity, pointer_position, paths) in drops {
+        let Ok((_, _, _, input, _)) = node_query.get(entity) else {
+            continue;
+        };
+
+        match input.file_drop {
+            FileDropPolicy::Ignore => {}
+            FileDropPolicy::Path => {
+                let text = paths
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                texts.push((entity, pointer_position, text));
+            }
+            FileDropPolicy::Contents { max_bytes } => {
+                let task = IoTaskPool::get().spawn(async move {
+                    paths
+                        .into_iter()
+                        .map(|path| {
+                            let result = read_dropped_file(&path, max_bytes);
+                            (path, result)
+                        })
+                        .collect()
+                });
+                pending_reads.push(PendingFileRead {
+                    entity,
+                    pointer_position,
+                    task,
+                });
+            }
+        }
+    }
+
+    pending_reads.retain_mut(|pending_read| {
+        let Some(results) = check_ready(&mut pending_read.task) else {
+            return true;
+        };
+
+        let mut contents = vec![];
+        for (path, result) in results {
+            match result {
+                Ok(text) => contents.push(text),
+                Err(error) => warn!(
+                    "Can't insert the file \"{}\" dropped onto {}: {error}",
+                    path.display(),
+                    pending_read.entity
+                ),
+            }
+        }
+
+        if !contents.is_empty() {
+            texts.push((
+                pending_read.entity,
+                pending_read.pointer_position,
+                contents.join("\n"),
+            ));
+        }
+        false
+    });
+
+    for (entity, pointer_position, text) in texts {
+        let Ok((node, transform, buffer, input, mut queue)) = node_query.get_mut(entity) else {
+            continue;
+        };
+
+        // The input may have been disabled while its files were being read
+        if !input.is_enabled || input.read_only {
+            continue;
+        }
+
+        let position = buffer.editor.with_buffer(|buffer| {
+            pointer_to_text_position(node, transform, buffer, pointer_position)
+        });
+
+        queue.add(TextInputAction::Edit(TextInputEdit::Drop {
+            x: position.x as i32,
+            y: position.y as i32,
+            text,
+        }));
+        input_focus.set(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_before_a_deletion_are_unchanged() {
+        let (start, end) = (Cursor::new(1, 4), Cursor::new(2, 3));
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(0, 7), start, end),
+            Cursor::new(0, 7)
+        );
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(1, 2), start, end),
+            Cursor::new(1, 2)
+        );
+    }
+
+    #[test]
+    fn cursors_after_a_deletion_are_shifted_back() {
+        // Within a single line
+        let (start, end) = (Cursor::new(0, 2), Cursor::new(0, 5));
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(0, 8), start, end),
+            Cursor::new(0, 5)
+        );
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(1, 8), start, end),
+            Cursor::new(1, 8)
+        );
+
+        // Across lines, the rest of the end line is joined onto the start line
+        let (start, end) = (Cursor::new(1, 4), Cursor::new(3, 2));
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(3, 6), start, end),
+            Cursor::new(1, 8)
+        );
+        assert_eq!(
+            cursor_after_deletion(Cursor::new(5, 1), start, end),
+            Cursor::new(3, 1)
+        );
+    }
+
+    #[test]
+    fn selection_bounds_are_inclusive() {
+        let (start, end) = (Cursor::new(1, 4), Cursor::new(2, 3));
+        assert!(is_within_selection(start, start, end));
+        assert!(is_within_selection(end, start, end));
+        assert!(is_within_selection(Cursor::new(1, 9), start, end));
+        assert!(!is_within_selection(Cursor::new(1, 3), start, end));
+        assert!(!is_within_selection(Cursor::new(2, 4), start, end));
+    }
+}
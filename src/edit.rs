@@ -1,50 +1,93 @@
+use crate::SelectionOnBlur;
+use crate::ShortcutKeys;
 use crate::SubmitText;
+use crate::TextInputAutoSize;
 use crate::TextInputBuffer;
+use crate::TextInputCaret;
+use crate::TextInputClickSettings;
+use crate::TextInputContents;
+use crate::TextInputCursorBlink;
+use crate::TextInputEditMode;
+use crate::TextInputEditModeChanged;
 use crate::TextInputFilter;
 use crate::TextInputGlobalState;
+use crate::TextInputKeyboardConsumption;
+use crate::TextInputLogView;
 use crate::TextInputMode;
 use crate::TextInputNode;
+use crate::TextInputPrompt;
 use crate::TextInputQueue;
+use crate::TextInputReleasedText;
 use crate::TextInputStyle;
 use crate::actions::TextInputAction;
 use crate::actions::TextInputEdit;
-use crate::actions::apply_text_input_edit;
+use crate::actions::apply_edit;
 use crate::clipboard::Clipboard;
+use crate::context_menu::TextInputContextMenuPanel;
+use crate::context_menu::is_in_context_menu;
+use crate::drag_and_drop::SelectionDragData;
+use crate::drag_and_drop::TextInputDropCaret;
+use crate::drag_and_drop::accepts_drop;
+use crate::drag_and_drop::drop_caret_at;
+use crate::drag_and_drop::is_within_selection;
+use crate::drag_and_drop::pointer_to_text_position;
 use crate::text_input_pipeline::TextInputPipeline;
+use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::change_detection::DetectChangesMut;
 use bevy::ecs::component::Component;
+use bevy::ecs::component::Tick;
 use bevy::ecs::entity::Entity;
+use bevy::ecs::hierarchy::ChildOf;
+use bevy::ecs::message::MessageMutator;
 use bevy::ecs::message::MessageReader;
 use bevy::ecs::message::MessageWriter;
 use bevy::ecs::observer::On;
+use bevy::ecs::query::Has;
+use bevy::ecs::query::With;
 use bevy::ecs::system::Commands;
+use bevy::ecs::system::Local;
 use bevy::ecs::system::Query;
 use bevy::ecs::system::Res;
 use bevy::ecs::system::ResMut;
+use bevy::ecs::system::SystemChangeTick;
+use bevy::ecs::world::Ref;
+use bevy::input::ButtonInput;
 use bevy::input::ButtonState;
 use bevy::input::keyboard::Key;
+use bevy::input::keyboard::KeyCode;
 use bevy::input::keyboard::KeyboardInput;
+use bevy::input::keyboard::NativeKey;
+use bevy::input::keyboard::NativeKeyCode;
 use bevy::input::mouse::MouseScrollUnit;
 use bevy::input::mouse::MouseWheel;
 use bevy::input_focus::FocusedInput;
 use bevy::input_focus::InputFocus;
 use bevy::math::Rect;
-use bevy::picking::events::Click;
+use bevy::math::Vec2;
 use bevy::picking::events::Drag;
-use bevy::picking::events::Move;
+use bevy::picking::events::DragEnd;
 use bevy::picking::events::Pointer;
 use bevy::picking::events::Press;
 use bevy::picking::hover::HoverMap;
 use bevy::picking::pointer::PointerButton;
+use bevy::text::TextFont;
 use bevy::time::Time;
 use bevy::ui::ComputedNode;
+use bevy::ui::ComputedUiRenderTargetInfo;
 use bevy::ui::UiGlobalTransform;
 use cosmic_text::Action;
+use cosmic_text::Attrs;
+use cosmic_text::AttrsList;
 use cosmic_text::BorrowedWithFontSystem;
+use cosmic_text::BufferLine;
 use cosmic_text::Change;
+use cosmic_text::Cursor;
 use cosmic_text::Edit;
 use cosmic_text::Editor;
+use cosmic_text::LineEnding;
 use cosmic_text::Motion;
 use cosmic_text::Selection;
+use cosmic_text::Shaping;
 
 pub fn apply_action<'a>(
     editor: &mut BorrowedWithFontSystem<Editor<'a>>,
@@ -86,6 +129,14 @@ pub fn buffer_len(buffer: &cosmic_text::Buffer) -> usize {
         .sum()
 }
 
+/// Selects all of the text, leaving the cursor at the end
+pub(crate) fn select_all(editor: &mut BorrowedWithFontSystem<Editor<'_>>) {
+    editor.action(Action::Motion(Motion::BufferStart));
+    let cursor = editor.cursor();
+    editor.set_selection(Selection::Normal(cursor));
+    editor.action(Action::Motion(Motion::BufferEnd));
+}
+
 pub fn cursor_at_line_end(editor: &mut BorrowedWithFontSystem<Editor<'_>>) -> bool {
     let cursor = editor.cursor();
     editor.with_buffer(|buffer| {
@@ -97,10 +148,177 @@ pub fn cursor_at_line_end(editor: &mut BorrowedWithFontSystem<Editor<'_>>) -> bo
     })
 }
 
+/// Number of visual lines in the buffer after wrapping.
+/// Lines keep their layouts between edits, so only lines that were edited, or haven't been laid out yet, are laid out.
+pub fn visual_line_count(buffer: &mut BorrowedWithFontSystem<cosmic_text::Buffer>) -> usize {
+    visual_line_count_up_to(buffer, usize::MAX)
+}
+
+/// Counts the visual lines in the buffer, stopping once the count exceeds `limit`
+fn visual_line_count_up_to(
+    buffer: &mut BorrowedWithFontSystem<cosmic_text::Buffer>,
+    limit: usize,
+) -> usize {
+    if buffer.wrap() == cosmic_text::Wrap::None {
+        return buffer.lines.len();
+    }
+
+    let mut count = 0;
+    for line_i in 0..buffer.lines.len() {
+        count += match buffer.lines[line_i].layout_opt() {
+            Some(layout) => layout.len().max(1),
+            None => buffer
+                .line_layout(line_i)
+                .map_or(1, |layout| layout.len().max(1)),
+        };
+        if limit < count {
+            break;
+        }
+    }
+    count
+}
+
+/// True if the editor's text has more lines than permitted by the input's `max_lines` or `max_visual_lines`
+pub fn exceeds_line_limits(
+    editor: &mut BorrowedWithFontSystem<Editor<'_>>,
+    input: &TextInputNode,
+) -> bool {
+    if !matches!(input.mode, TextInputMode::MultiLine { .. }) {
+        return false;
+    }
+
+    let line_count = editor.with_buffer(|buffer| buffer.lines.len());
+
+    if input
+        .max_lines
+        .is_some_and(|max_lines| max_lines < line_count)
+    {
+        return true;
+    }
+
+    input.max_visual_lines.is_some_and(|max_visual_lines| {
+        // Each line has at least one visual line
+        max_visual_lines < line_count
+            || max_visual_lines
+                < editor.with_buffer_mut(|buffer| visual_line_count_up_to(buffer, max_visual_lines))
+    })
+}
+
+/// Frees the shaping and layout caches of the buffer's lines, keeping their text and attributes.
+/// The lines are reshaped the next time they are needed.
+pub fn release_buffer_caches(buffer: &mut cosmic_text::Buffer) {
+    for line in buffer.lines.iter_mut() {
+        let mut released = cosmic_text::BufferLine::new(
+            line.text(),
+            line.ending(),
+            line.attrs_list().clone(),
+            cosmic_text::Shaping::Advanced,
+        );
+        released.set_align(line.align());
+        *line = released;
+    }
+}
+
+/// Appends `text` to the end of the editor's buffer as one or more new lines, without moving the view or recording an undo change.
+/// With a `TextInputLogView`, the oldest lines beyond its `max_lines` are removed, and the view follows the new lines if it was scrolled to the bottom.
+/// Removing lines clears the undo history.
+pub fn append_lines(
+    editor: &mut BorrowedWithFontSystem<Editor<'static>>,
+    changes: &mut cosmic_undo_2::Commands<Change>,
+    text: &str,
+    log_view: Option<&TextInputLogView>,
+) {
+    let follow = log_view.is_some_and(|log_view| log_view.auto_scroll)
+        && editor.with_buffer(|buffer| {
+            buffer
+                .layout_runs()
+                .last()
+                .is_none_or(|run| buffer.lines.len() <= run.line_i + 1)
+        });
+    let scroll = editor.with_buffer(|buffer| buffer.scroll());
+
+    let removed = editor.with_buffer_mut(|buffer| {
+        let (attrs_list, align) = buffer.lines.last().map_or_else(
+            || (AttrsList::new(&Attrs::new()), None),
+            |line| (line.attrs_list().clone(), line.align()),
+        );
+
+        if is_buffer_empty(buffer) {
+            buffer.lines.clear();
+        }
+
+        let new_lines = text.lines().chain(text.is_empty().then_some(""));
+        for line in new_lines {
+            let mut buffer_line = BufferLine::new(
+                line,
+                LineEnding::default(),
+                attrs_list.clone(),
+                Shaping::Advanced,
+            );
+            buffer_line.set_align(align);
+            buffer.lines.push(buffer_line);
+        }
+
+        let removed = log_view
+            .and_then(|log_view| log_view.max_lines)
+            .map_or(0, |max_lines| {
+                buffer.lines.len().saturating_sub(max_lines.max(1))
+            });
+        buffer.lines.drain(..removed);
+        buffer.set_redraw(true);
+        removed
+    });
+
+    if 0 < removed {
+        *changes = Default::default();
+
+        let shift = |cursor: Cursor| {
+            if cursor.line < removed {
+                Cursor::new(0, 0)
+            } else {
+                Cursor {
+                    line: cursor.line - removed,
+                    ..cursor
+                }
+            }
+        };
+        let cursor = shift(editor.cursor());
+        editor.set_cursor(cursor);
+        let selection = match editor.selection() {
+            Selection::None => Selection::None,
+            Selection::Normal(cursor) => Selection::Normal(shift(cursor)),
+            Selection::Line(cursor) => Selection::Line(shift(cursor)),
+            Selection::Word(cursor) => Selection::Word(shift(cursor)),
+        };
+        editor.set_selection(selection);
+
+        // Shape now so the view isn't scrolled to the moved cursor
+        editor.shape_as_needed(false);
+    }
+
+    editor.with_buffer_mut(|buffer| {
+        let mut scroll = scroll;
+        if follow {
+            // Scrolling past the end moves the view back to show the last lines
+            scroll.line = buffer.lines.len() - 1;
+            scroll.vertical = 0.;
+        } else {
+            scroll.line = scroll.line.saturating_sub(removed);
+        }
+        buffer.set_scroll(scroll);
+    });
+}
+
 pub(crate) fn is_buffer_empty(buffer: &cosmic_text::Buffer) -> bool {
     buffer.lines.is_empty() || (buffer.lines.len() == 1 && buffer.lines[0].text().is_empty())
 }
 
+/// The last pointer position of a drag selection, used to auto-scroll while the pointer is outside of the input
+#[derive(Component)]
+pub struct DragAutoScrollData {
+    pointer_position: Vec2,
+}
+
 pub(crate) fn on_drag_text_input(
     trigger: On<Pointer<Drag>>,
     mut node_query: Query<(
@@ -108,9 +326,11 @@ pub(crate) fn on_drag_text_input(
         &UiGlobalTransform,
         &mut TextInputBuffer,
         &TextInputNode,
+        Option<&mut SelectionDragData>,
     )>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     input_focus: Res<InputFocus>,
+    mut commands: Commands,
 ) {
     if trigger.button != PointerButton::Primary {
         return;
@@ -123,7 +343,9 @@ pub(crate) fn on_drag_text_input(
         return;
     }
 
-    let Ok((node, transform, mut buffer, input)) = node_query.get_mut(trigger.entity) else {
+    let Ok((node, transform, mut buffer, input, selection_drag)) =
+        node_query.get_mut(trigger.entity)
+    else {
         return;
     };
 
@@ -136,6 +358,48 @@ pub(crate) fn on_drag_text_input(
     let position =
         trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
 
+    // Dragging the selected text shows where it will be dropped instead of changing the selection.
+    // Picking doesn't send `DragOver` events to the dragged input itself, so its drop caret is updated here.
+    if let Some(mut selection_drag) = selection_drag {
+        selection_drag.dragging = true;
+
+        let selection = buffer.editor.selection_bounds();
+        let drop_caret = if Rect::from_corners(Vec2::ZERO, node.size()).contains(position)
+            && accepts_drop(input)
+        {
+            buffer.editor.with_buffer(|buffer| {
+                let position = pointer_to_text_position(
+                    node,
+                    transform,
+                    buffer,
+                    trigger.pointer_location.position,
+                );
+                drop_caret_at(buffer, position, selection)
+            })
+        } else {
+            None
+        };
+
+        match drop_caret {
+            Some(drop_caret) => {
+                commands.entity(trigger.entity).insert(drop_caret);
+            }
+            None => {
+                commands
+                    .entity(trigger.entity)
+                    .try_remove::<TextInputDropCaret>();
+            }
+        }
+        return;
+    }
+
+    // Outside of the input the selection is extended by `auto_scroll_dragged_text_inputs` as the view scrolls
+    let position = position.clamp(Vec2::ZERO, (node.size() - 1.).max(Vec2::ZERO));
+
+    commands.entity(trigger.entity).insert(DragAutoScrollData {
+        pointer_position: trigger.pointer_location.position,
+    });
+
     let mut editor = buffer
         .editor
         .borrow_with(&mut text_input_pipeline.font_system);
@@ -148,22 +412,143 @@ pub(crate) fn on_drag_text_input(
     });
 }
 
+/// Ends drag selections and auto-scrolling.
+/// Selected text dragged within its own input is dropped here, moved or copied if Ctrl or Command is held.
+pub(crate) fn on_drag_end_text_input(
+    trigger: On<Pointer<DragEnd>>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &TextInputBuffer,
+        &mut TextInputQueue,
+        Has<SelectionDragData>,
+        Has<TextInputDropCaret>,
+    )>,
+    global_state: Res<TextInputGlobalState>,
+    mut commands: Commands,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    commands
+        .entity(trigger.entity)
+        .try_remove::<(DragAutoScrollData, SelectionDragData, TextInputDropCaret)>();
+
+    // The drop caret is only shown while the pointer is over the input and outside of the dragged selection
+    let Ok((node, transform, buffer, mut queue, true, true)) = node_query.get_mut(trigger.entity)
+    else {
+        return;
+    };
+
+    let position = buffer.editor.with_buffer(|buffer| {
+        pointer_to_text_position(node, transform, buffer, trigger.pointer_location.position)
+    });
+    let (x, y) = (position.x as i32, position.y as i32);
+
+    if global_state.command {
+        if let Some(text) = buffer.editor.copy_selection() {
+            queue.add(TextInputAction::Edit(TextInputEdit::Drop { x, y, text }));
+        }
+    } else {
+        queue.add(TextInputAction::Edit(TextInputEdit::MoveSelection { x, y }));
+    }
+}
+
+/// Scrolls the focused input while its selection is dragged past its edges.
+/// The scroll speed is proportional to the distance of the pointer outside of the input,
+/// and scrolling continues while the button is held, even if the pointer stops moving.
+pub fn auto_scroll_dragged_text_inputs(
+    mut node_query: Query<(
+        &ComputedNode,
+        &UiGlobalTransform,
+        &mut TextInputBuffer,
+        &TextInputNode,
+        &DragAutoScrollData,
+    )>,
+    mut text_input_pipeline: ResMut<TextInputPipeline>,
+    input_focus: Res<InputFocus>,
+    click_settings: Res<TextInputClickSettings>,
+    time: Res<Time>,
+) {
+    let Some(entity) = input_focus.get() else {
+        return;
+    };
+
+    let Ok((node, transform, mut buffer, input, drag_data)) = node_query.get_mut(entity) else {
+        return;
+    };
+
+    if !input.is_enabled {
+        return;
+    }
+
+    let rect = Rect::from_center_size(transform.translation, node.size());
+    let position = drag_data.pointer_position * node.inverse_scale_factor().recip() - rect.min;
+    let clamped_position = position.clamp(Vec2::ZERO, (node.size() - 1.).max(Vec2::ZERO));
+    let overflow = position - clamped_position;
+    if overflow == Vec2::ZERO {
+        return;
+    }
+
+    let delta = overflow * click_settings.drag_scroll_speed * time.delta_secs();
+
+    let mut editor = buffer
+        .editor
+        .borrow_with(&mut text_input_pipeline.font_system);
+
+    let scroll = editor.with_buffer_mut(|buffer| {
+        let max_line_width = buffer
+            .layout_runs()
+            .map(|run| run.line_w)
+            .fold(0., f32::max);
+        let view_width = buffer.size().0.unwrap_or(node.size().x);
+
+        let mut scroll = buffer.scroll();
+        scroll.vertical += delta.y;
+        scroll.horizontal = (scroll.horizontal + delta.x)
+            .min(max_line_width - view_width)
+            .max(0.);
+        buffer.set_scroll(scroll);
+
+        // Keeps the vertical scroll within the text
+        buffer.shape_until_scroll(false);
+        buffer.scroll()
+    });
+
+    editor.action(Action::Drag {
+        x: clamped_position.x as i32 + scroll.horizontal as i32,
+        y: clamped_position.y as i32,
+    });
+}
+
+/// Moves the cursor to the pressed position. Shift-clicking extends the selection.
+/// Double, triple and quadruple clicks select a word, a paragraph and all of the text, and dragging after a double or triple click extends the selection by words or paragraphs.
 pub(crate) fn on_text_input_pressed(
     trigger: On<Pointer<Press>>,
     mut node_query: Query<(
         &ComputedNode,
         &UiGlobalTransform,
-        &mut TextInputBuffer,
+        Option<&mut TextInputBuffer>,
         &TextInputNode,
+        Option<&mut MultiClickData>,
+        Option<&TextInputReleasedText>,
+        &mut TextInputQueue,
     )>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut input_focus: ResMut<InputFocus>,
+    global_state: Res<TextInputGlobalState>,
+    click_settings: Res<TextInputClickSettings>,
+    time: Res<Time>,
+    mut commands: Commands,
 ) {
     if trigger.button != PointerButton::Primary {
         return;
     }
 
-    let Ok((node, transform, mut buffer, input)) = node_query.get_mut(trigger.entity) else {
+    let Ok((node, transform, buffer, input, multi_click_data, released, mut queue)) =
+        node_query.get_mut(trigger.entity)
+    else {
         return;
     };
 
@@ -171,10 +556,9 @@ pub(crate) fn on_text_input_pressed(
         return;
     }
 
-    if input_focus
-        .get()
-        .is_none_or(|active_input| active_input != trigger.entity)
-    {
+    // The modifier key state is only tracked while an input is focused
+    let was_focused = input_focus.get() == Some(trigger.entity);
+    if !was_focused {
         input_focus.set(trigger.entity);
     }
 
@@ -183,16 +567,86 @@ pub(crate) fn on_text_input_pressed(
     let position =
         trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
 
+    // A released input's buffer is restored now that it's focused, and the click is applied from its queue once it is
+    let Some(mut buffer) = buffer else {
+        if let Some(released) = released {
+            queue.add(TextInputAction::Edit(TextInputEdit::Click {
+                x: position.x as i32 + released.scroll.horizontal as i32,
+                y: position.y as i32,
+            }));
+        }
+        return;
+    };
+
     let mut editor = buffer
         .editor
         .borrow_with(&mut text_input_pipeline.font_system);
 
+    // The input's lines may have been released while it was unfocused, and need to be shaped before hit testing.
+    editor.shape_as_needed(false);
+
     let scroll = editor.with_buffer(|buffer| buffer.scroll());
+    let x = position.x as i32 + scroll.horizontal as i32;
+    let y = position.y as i32;
 
-    editor.action(Action::Click {
-        x: position.x as i32 + scroll.horizontal as i32,
-        y: position.y as i32,
-    });
+    let now = time.elapsed_secs();
+    let pointer_position = trigger.pointer_location.position;
+    let click_count = match multi_click_data {
+        Some(mut multi_click_data) => {
+            if now - multi_click_data.last_press_time <= click_settings.multi_click_time
+                && multi_click_data
+                    .last_press_position
+                    .distance(pointer_position)
+                    <= click_settings.multi_click_distance
+            {
+                multi_click_data.click_count = (multi_click_data.click_count + 1).min(4);
+            } else {
+                multi_click_data.click_count = 1;
+            }
+            multi_click_data.last_press_time = now;
+            multi_click_data.last_press_position = pointer_position;
+            multi_click_data.click_count
+        }
+        None => {
+            commands.entity(trigger.entity).insert(MultiClickData {
+                last_press_time: now,
+                last_press_position: pointer_position,
+                click_count: 1,
+            });
+            1
+        }
+    };
+
+    // Pressing inside the selection may start dragging the selected text, see `SelectionDragData`
+    let selection_pressed = click_count == 1
+        && was_focused
+        && !global_state.shift
+        && input.allow_drag_and_drop
+        && editor
+            .selection_bounds()
+            .filter(|(start, end)| start != end)
+            .zip(editor.with_buffer(|buffer| buffer.hit(x as f32, y as f32)))
+            .is_some_and(|((start, end), cursor)| is_within_selection(cursor, start, end));
+
+    if selection_pressed {
+        commands
+            .entity(trigger.entity)
+            .insert(SelectionDragData { dragging: false });
+        return;
+    }
+
+    commands
+        .entity(trigger.entity)
+        .try_remove::<SelectionDragData>();
+
+    match click_count {
+        // Dragging from the cursor extends the selection, keeping the granularity of word and paragraph selections
+        1 if was_focused && global_state.shift => editor.action(Action::Drag { x, y }),
+        1 => editor.action(Action::Click { x, y }),
+        2 => editor.action(Action::DoubleClick { x, y }),
+        3 => editor.action(Action::TripleClick { x, y }),
+        _ => select_all(&mut editor),
+    }
 }
 
 /// Updates the scroll position of scrollable nodes in response to mouse input
@@ -231,126 +685,91 @@ pub fn mouse_wheel_scroll(
     }
 }
 
-const MULTI_CLICK_PERIOD: f32 = 0.5; // seconds
-
+/// Tracks consecutive presses on a text input to detect double, triple and quadruple clicks
 #[derive(Component)]
 pub struct MultiClickData {
-    last_click_time: f32,
+    last_press_time: f32,
+    last_press_position: Vec2,
     click_count: usize,
 }
 
-pub fn on_multi_click_set_selection(
-    click: On<Pointer<Click>>,
-    time: Res<Time>,
-    mut text_input_nodes: Query<(
-        &TextInputNode,
-        &mut TextInputQueue,
-        &mut TextInputBuffer,
-        &UiGlobalTransform,
-        &ComputedNode,
-    )>,
-    mut multi_click_datas: Query<&mut MultiClickData>,
-    mut text_input_pipeline: ResMut<TextInputPipeline>,
-    mut commands: Commands,
-) {
-    if click.button != PointerButton::Primary {
-        return;
-    }
-
-    let entity = click.entity;
-
-    let Ok((input, mut queue, mut buffer, transform, node)) = text_input_nodes.get_mut(entity)
-    else {
-        return;
+/// The text typed by a key press, without any control characters.
+/// Uses `KeyboardInput::text` which includes characters composed from dead keys and characters typed with AltGr,
+/// falling back to the logical key on platforms that don't report text.
+fn typed_text(keyboard_input: &KeyboardInput) -> impl Iterator<Item = char> + '_ {
+    let text = match (&keyboard_input.text, &keyboard_input.logical_key) {
+        (Some(text), _) => text.as_str(),
+        (None, Key::Character(str)) => str.as_str(),
+        (None, Key::Space) => " ",
+        _ => "",
     };
+    text.chars().filter(|char| !char.is_control())
+}
 
-    if !input.is_enabled || !input.focus_on_pointer_down {
-        return;
-    }
-
-    let now = time.elapsed_secs();
-    if let Ok(mut multi_click_data) = multi_click_datas.get_mut(entity)
-        && now - multi_click_data.last_click_time
-            <= MULTI_CLICK_PERIOD * multi_click_data.click_count as f32
+/// The lowercase Latin letter of a key, for matching shortcuts
+fn shortcut_char(keyboard_input: &KeyboardInput, shortcut_keys: ShortcutKeys) -> Option<char> {
+    if shortcut_keys == ShortcutKeys::Logical
+        && let Key::Character(str) = &keyboard_input.logical_key
+        && let Some(char) = str.chars().next()
+        && char.is_ascii_alphabetic()
     {
-        let rect = Rect::from_center_size(transform.translation, node.size());
-
-        let position =
-            click.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
-        let mut editor = buffer
-            .editor
-            .borrow_with(&mut text_input_pipeline.font_system);
-        let scroll = editor.with_buffer(|buffer| buffer.scroll());
-        match multi_click_data.click_count {
-            1 => {
-                multi_click_data.click_count += 1;
-                multi_click_data.last_click_time = now;
-
-                queue.add(TextInputAction::Edit(TextInputEdit::DoubleClick {
-                    x: position.x as i32 + scroll.horizontal as i32,
-                    y: position.y as i32,
-                }));
-                return;
-            }
-            2 => {
-                editor.action(Action::Motion(Motion::ParagraphStart));
-                let cursor = editor.cursor();
-                editor.set_selection(Selection::Normal(cursor));
-                editor.action(Action::Motion(Motion::ParagraphEnd));
-                if let Ok(mut entity) = commands.get_entity(entity) {
-                    entity.try_remove::<MultiClickData>();
-                }
-                return;
-            }
-            _ => (),
-        }
+        // convert to lowercase so that the commands work with capslock on
+        return Some(char.to_ascii_lowercase());
     }
-    if let Ok(mut entity) = commands.get_entity(entity) {
-        entity.try_insert(MultiClickData {
-            last_click_time: now,
-            click_count: 1,
-        });
-    }
-}
 
-pub fn on_move_clear_multi_click(move_: On<Pointer<Move>>, mut commands: Commands) {
-    if let Ok(mut entity) = commands.get_entity(move_.entity) {
-        entity.try_remove::<MultiClickData>();
+    // Non-Latin layouts fall back to the letter of the physical key on a US layout
+    match keyboard_input.key_code {
+        KeyCode::KeyA => Some('a'),
+        KeyCode::KeyC => Some('c'),
+        KeyCode::KeyV => Some('v'),
+        KeyCode::KeyX => Some('x'),
+        KeyCode::KeyY => Some('y'),
+        KeyCode::KeyZ => Some('z'),
+        _ => None,
     }
 }
 
 pub fn queue_text_input_action(
-    input_mode: &TextInputMode,
-    shift_pressed: &mut bool,
+    input: &TextInputNode,
+    global_state: &mut TextInputGlobalState,
     overwrite_mode: &mut bool,
-    command_pressed: &mut bool,
     keyboard_input: &KeyboardInput,
     mut queue: impl FnMut(TextInputAction),
 ) {
+    let is_pressed = keyboard_input.state == ButtonState::Pressed;
     match keyboard_input.logical_key {
         Key::Shift => {
-            *shift_pressed = keyboard_input.state == ButtonState::Pressed;
+            global_state.shift = is_pressed;
             return;
         }
         Key::Control => {
-            *command_pressed = keyboard_input.state == ButtonState::Pressed;
+            global_state.command = is_pressed;
             return;
         }
         #[cfg(target_os = "macos")]
         Key::Super => {
-            *command_pressed = keyboard_input.state == ButtonState::Pressed;
+            global_state.command = is_pressed;
+            return;
+        }
+        Key::Alt => {
+            global_state.alt = is_pressed;
+            return;
+        }
+        Key::AltGraph => {
+            global_state.alt_graph = is_pressed;
             return;
         }
         _ => {}
     };
 
+    let shift_pressed = &global_state.shift;
+
     if keyboard_input.state.is_pressed() {
-        if *command_pressed {
+        if global_state.command && !global_state.is_alt_graph() {
             match &keyboard_input.logical_key {
-                Key::Character(str) => {
-                    if let Some(char) = str.chars().next() {
-                        // convert to lowercase so that the commands work with capslock on
-                        match (char.to_ascii_lowercase(), *shift_pressed) {
+                Key::Character(_) => {
+                    if let Some(char) = shortcut_char(keyboard_input, global_state.shortcut_keys) {
+                        match (char, *shift_pressed) {
                             ('c', false) => {
                                 // copy
                                 queue(TextInputAction::Copy);
@@ -396,12 +815,12 @@ pub fn queue_text_input_action(
                     )));
                 }
                 Key::ArrowUp => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
+                    if matches!(input.mode, TextInputMode::MultiLine { .. }) {
                         queue(TextInputAction::Edit(TextInputEdit::Scroll { lines: -1 }));
                     }
                 }
                 Key::ArrowDown => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
+                    if matches!(input.mode, TextInputMode::MultiLine { .. }) {
                         queue(TextInputAction::Edit(TextInputEdit::Scroll { lines: 1 }));
                     }
                 }
@@ -423,21 +842,8 @@ pub fn queue_text_input_action(
             }
         } else {
             match &keyboard_input.logical_key {
-                Key::Character(_) | Key::Space => {
-                    let str = if let Key::Character(str) = &keyboard_input.logical_key {
-                        str.chars()
-                    } else {
-                        " ".chars()
-                    };
-                    for char in str {
-                        queue(TextInputAction::Edit(TextInputEdit::Insert(
-                            char,
-                            *overwrite_mode,
-                        )));
-                    }
-                }
-                Key::Enter => match (*shift_pressed, input_mode) {
-                    (false, TextInputMode::MultiLine { .. }) => {
+                Key::Enter => match (*shift_pressed == input.submit_on_enter, input.mode) {
+                    (true, TextInputMode::MultiLine { .. }) => {
                         queue(TextInputAction::Edit(TextInputEdit::Enter));
                     }
                     _ => {
@@ -506,7 +912,7 @@ pub fn queue_text_input_action(
                     queue(TextInputAction::Edit(TextInputEdit::Escape));
                 }
                 Key::Tab => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
+                    if matches!(input.mode, TextInputMode::MultiLine { .. }) {
                         if *shift_pressed {
                             queue(TextInputAction::Edit(TextInputEdit::Unindent));
                         } else {
@@ -514,31 +920,274 @@ pub fn queue_text_input_action(
                         }
                     }
                 }
-                Key::Insert => {
-                    if !*shift_pressed {
-                        *overwrite_mode = !*overwrite_mode;
+                Key::Insert if !*shift_pressed && input.allow_overwrite_mode => {
+                    *overwrite_mode = !*overwrite_mode;
+                }
+                _ => {
+                    // Dead keys type nothing, their composed character is in the text of the next key press
+                    for char in typed_text(keyboard_input) {
+                        queue(TextInputAction::Edit(TextInputEdit::Insert(
+                            char,
+                            *overwrite_mode && input.allow_overwrite_mode,
+                        )));
                     }
                 }
-                _ => {}
             }
         }
     }
 }
 
-/// updates the cursor blink time for text inputs
+/// updates the cursor blink time for the focused text input
 pub fn cursor_blink_system(
-    mut query: Query<(&mut TextInputBuffer, &TextInputStyle, &TextInputQueue)>,
+    input_focus: Res<InputFocus>,
+    mut query: Query<(&mut TextInputCursorBlink, &TextInputStyle, &TextInputQueue)>,
     time: Res<Time>,
 ) {
-    for (mut buffer, style, queue) in query.iter_mut() {
-        buffer.cursor_blink_time = if queue.is_empty() {
-            (buffer.cursor_blink_time + time.delta_secs()).rem_euclid(style.blink_interval * 2.)
-        } else {
-            0.
+    let Some((mut blink, style, queue)) = input_focus
+        .get()
+        .and_then(|entity| query.get_mut(entity).ok())
+    else {
+        return;
+    };
+
+    // Restart the blink cycle when the input gains focus or is edited, so the cursor is immediately visible
+    blink.time = if input_focus.is_changed() || !queue.is_empty() {
+        0.
+    } else {
+        (blink.time + time.delta_secs()).rem_euclid(style.blink_interval * 2.)
+    };
+}
+
+/// Eases each displayed cursor towards its position in the text, and measures the glyph it covers
+pub fn text_input_caret_system(
+    input_focus: Res<InputFocus>,
+    mut query: Query<(
+        Entity,
+        &TextInputBuffer,
+        &TextInputStyle,
+        &mut TextInputCaret,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, buffer, style, mut caret) in query.iter_mut() {
+        if input_focus.get() != Some(entity) && style.unfocused_caret_shape.is_none() {
+            caret.set_if_neq(TextInputCaret::default());
+            continue;
+        }
+
+        let target = buffer
+            .editor
+            .cursor_position()
+            .map(|(x, y)| Vec2::new(x as f32, y as f32));
+
+        let cursor = buffer.editor.cursor();
+        let glyph_width = buffer.editor.with_buffer(|buffer| {
+            buffer
+                .layout_runs()
+                .filter(|run| run.line_i == cursor.line)
+                .flat_map(|run| run.glyphs.iter())
+                .find(|glyph| glyph.start == cursor.index)
+                .map_or(0.5 * buffer.metrics().font_size, |glyph| glyph.w)
+        });
+
+        let position = match (caret.position, target) {
+            (Some(position), Some(target)) if 0. < style.caret_motion_time => {
+                let t = 1. - (-time.delta_secs() / style.caret_motion_time).exp();
+                let next = position.lerp(target, t);
+                // Snap to the target once within half a pixel
+                Some(if next.distance_squared(target) < 0.25 {
+                    target
+                } else {
+                    next
+                })
+            }
+            _ => target,
         };
+
+        caret.set_if_neq(TextInputCaret {
+            position,
+            glyph_width,
+        });
+    }
+}
+
+/// Applies the `selection_on_blur` policy of inputs when they lose focus,
+/// and releases the undo history and shaping caches of inputs with `release_on_blur` set.
+/// Idle inputs with `release_on_blur` set are then released entirely by [`release_idle_text_inputs`].
+pub fn release_blurred_text_inputs(
+    input_focus: Res<InputFocus>,
+    mut previous_focus: Local<Option<Entity>>,
+    mut query: Query<(&TextInputNode, &mut TextInputBuffer)>,
+) {
+    if !input_focus.is_changed() || input_focus.get() == *previous_focus {
+        return;
+    }
+
+    let Some(entity) = std::mem::replace(&mut *previous_focus, input_focus.get()) else {
+        return;
+    };
+
+    let Ok((input, mut buffer)) = query.get_mut(entity) else {
+        return;
+    };
+
+    if input.selection_on_blur == SelectionOnBlur::Clear {
+        buffer.editor.set_selection(Selection::None);
+    }
+
+    if input.release_on_blur {
+        buffer.changes = Default::default();
+        buffer.glyph_cache.clear();
+        buffer.editor.with_buffer_mut(release_buffer_caches);
     }
 }
 
+fn is_hovered(hover_map: &HoverMap, entity: Entity) -> bool {
+    hover_map
+        .values()
+        .any(|hovers| hovers.contains_key(&entity))
+}
+
+/// Replaces the `TextInputBuffer` of each idle unfocused input with `release_on_blur` set by a [`TextInputReleasedText`],
+/// once its text and prompt have been laid out and its `TextInputContents` updated.
+/// Inputs that are hovered, dragged or have queued actions, or that show a selection or an unfocused caret, keep their buffers.
+pub fn release_idle_text_inputs(
+    mut commands: Commands,
+    input_focus: Res<InputFocus>,
+    hover_map: Res<HoverMap>,
+    query: Query<(
+        Entity,
+        &TextInputNode,
+        &TextInputBuffer,
+        &TextInputQueue,
+        &TextInputStyle,
+        Option<&TextInputPrompt>,
+        Has<SelectionDragData>,
+    )>,
+) {
+    for (entity, input, buffer, queue, style, prompt, dragged) in query.iter() {
+        if !input.release_on_blur
+            || input_focus.get() == Some(entity)
+            || !queue.is_empty()
+            || dragged
+            || style.unfocused_caret_shape.is_some()
+            || buffer
+                .editor
+                .selection_bounds()
+                .is_some_and(|(start, end)| start != end)
+            || buffer.needs_update
+            || buffer.editor.redraw()
+            || buffer.text_changed
+            || prompt
+                .is_some_and(|prompt| !prompt.text.is_empty() && buffer.prompt_buffer.is_none())
+            || is_hovered(&hover_map, entity)
+        {
+            continue;
+        }
+
+        commands
+            .entity(entity)
+            .remove::<TextInputBuffer>()
+            .insert(TextInputReleasedText::new(buffer));
+    }
+}
+
+/// Restores the `TextInputBuffer` of released inputs that are focused, hovered or have queued actions,
+/// or whose settings, font, style, prompt, size or scale factor have changed since they were released, so they're laid out again.
+pub fn restore_released_text_inputs(
+    mut commands: Commands,
+    input_focus: Res<InputFocus>,
+    hover_map: Res<HoverMap>,
+    mut text_input_pipeline: ResMut<TextInputPipeline>,
+    query: Query<(
+        Entity,
+        Ref<TextInputReleasedText>,
+        &TextInputQueue,
+        Ref<TextInputNode>,
+        Ref<TextFont>,
+        Ref<TextInputStyle>,
+        Ref<ComputedNode>,
+        Ref<ComputedUiRenderTargetInfo>,
+        Option<Ref<TextInputPrompt>>,
+        Option<Ref<TextInputAutoSize>>,
+        Option<Ref<TextInputContents>>,
+    )>,
+    system_change_tick: SystemChangeTick,
+) {
+    for (
+        entity,
+        released,
+        queue,
+        input,
+        text_font,
+        style,
+        node,
+        target,
+        prompt,
+        auto_size,
+        contents,
+    ) in query.iter()
+    {
+        // Only changes made after the input was released need it to be laid out again
+        let changed =
+            |tick: Tick| tick.is_newer_than(released.added(), system_change_tick.this_run());
+
+        if input_focus.get() == Some(entity)
+            || !queue.is_empty()
+            || is_hovered(&hover_map, entity)
+            || changed(input.last_changed())
+            || changed(text_font.last_changed())
+            || changed(style.last_changed())
+            || changed(node.last_changed())
+            || changed(target.last_changed())
+            || prompt.is_some_and(|prompt| changed(prompt.last_changed()))
+            || auto_size.is_some_and(|auto_size| changed(auto_size.last_changed()))
+            || contents.is_some_and(|contents| changed(contents.added()))
+        {
+            commands
+                .entity(entity)
+                .remove::<TextInputReleasedText>()
+                .insert(released.restore(&mut text_input_pipeline.font_system));
+        }
+    }
+}
+
+/// Unfocuses the focused text input when the pointer is pressed outside of it, if its `unfocus_on_click_outside` is set.
+/// Presses on empty space are received by the window entity. Presses on the input's context menu don't unfocus it.
+pub fn unfocus_on_click_outside(
+    press: On<Pointer<Press>>,
+    mut input_focus: ResMut<InputFocus>,
+    input_query: Query<&TextInputNode>,
+    parent_query: Query<&ChildOf>,
+    panel_query: Query<Entity, With<TextInputContextMenuPanel>>,
+) {
+    // Only handle the press once, before it propagates
+    if press.button != PointerButton::Primary || press.entity != press.original_event_target() {
+        return;
+    }
+
+    let Some(focused) = input_focus.get() else {
+        return;
+    };
+
+    if !input_query
+        .get(focused)
+        .is_ok_and(|input| input.unfocus_on_click_outside)
+    {
+        return;
+    }
+
+    let target = press.entity;
+    if target == focused
+        || parent_query.iter_ancestors(target).any(|e| e == focused)
+        || is_in_context_menu(target, &panel_query, &parent_query)
+    {
+        return;
+    }
+
+    input_focus.clear();
+}
+
 pub fn process_text_input_queues(
     mut query: Query<(
         Entity,
@@ -546,16 +1195,27 @@ pub fn process_text_input_queues(
         &mut TextInputBuffer,
         &mut TextInputQueue,
         Option<&TextInputFilter>,
+        Option<&TextInputLogView>,
     )>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut submit_writer: MessageWriter<SubmitText>,
     mut clipboard: ResMut<Clipboard>,
+    mut input_focus: ResMut<InputFocus>,
 ) {
     let font_system = &mut text_input_pipeline.font_system;
+    let mut moved_from = vec![];
+
+    for (entity, node, mut buffer, mut actions_queue, maybe_filter, log_view) in query.iter_mut() {
+        if actions_queue.is_empty() {
+            continue;
+        }
 
-    for (entity, node, mut buffer, mut actions_queue, maybe_filter) in query.iter_mut() {
         let TextInputBuffer {
-            editor, changes, ..
+            editor,
+            changes,
+            text_changed,
+            document_extents,
+            ..
         } = &mut *buffer;
         let mut editor = editor.borrow_with(font_system);
         while let Some(action) = actions_queue.next() {
@@ -563,20 +1223,27 @@ pub fn process_text_input_queues(
                 TextInputAction::Submit => {
                     let text = editor.with_buffer(crate::get_text);
                     submit_writer.write(SubmitText { entity, text });
+                    if node.unfocus_on_submit && input_focus.get() == Some(entity) {
+                        input_focus.clear();
+                    }
                     if node.clear_on_submit {
                         actions_queue.add_front(TextInputAction::Edit(TextInputEdit::Delete));
                         actions_queue.add_front(TextInputAction::Edit(TextInputEdit::SelectAll));
                     }
                 }
                 TextInputAction::Cut => {
+                    if node.read_only {
+                        continue;
+                    }
                     if let Some(text) = editor.copy_selection() {
                         let _ = clipboard.set_text(text);
-                        apply_text_input_edit(
+                        *text_changed |= apply_edit(
                             TextInputEdit::Delete,
                             &mut editor,
                             changes,
-                            node.max_chars,
+                            node,
                             maybe_filter,
+                            Some(document_extents),
                         );
                     }
                 }
@@ -586,17 +1253,21 @@ pub fn process_text_input_queues(
                     }
                 }
                 TextInputAction::Paste => {
+                    if node.read_only {
+                        continue;
+                    }
                     actions_queue.add_front(TextInputAction::PasteDeferred(clipboard.fetch_text()));
                 }
                 TextInputAction::PasteDeferred(mut clipboard_read) => {
                     if let Some(text) = clipboard_read.poll_result() {
                         if let Ok(text) = text {
-                            apply_text_input_edit(
+                            *text_changed |= apply_edit(
                                 TextInputEdit::Paste(text),
                                 &mut editor,
                                 changes,
-                                node.max_chars,
+                                node,
                                 maybe_filter,
+                                Some(document_extents),
                             );
                         }
                     } else {
@@ -605,40 +1276,147 @@ pub fn process_text_input_queues(
                         break;
                     }
                 }
+                TextInputAction::Append(text) => {
+                    // An empty buffer's lines are replaced by the appended lines
+                    let (lines_before, cleared) = editor.with_buffer(|buffer| {
+                        let cleared = if is_buffer_empty(buffer) {
+                            buffer.lines.len()
+                        } else {
+                            0
+                        };
+                        (buffer.lines.len(), cleared)
+                    });
+                    append_lines(&mut editor, changes, &text, log_view);
+                    let lines_after = editor.with_buffer(|buffer| buffer.lines.len());
+                    let added = text.lines().count().max(1);
+                    let removed = lines_before - cleared + added - lines_after;
+                    document_extents.append_lines(cleared, added, removed);
+                    *text_changed = true;
+                }
+                TextInputAction::MoveFrom { source, text, x, y } => {
+                    if apply_edit(
+                        TextInputEdit::Drop {
+                            x,
+                            y,
+                            text: text.clone(),
+                        },
+                        &mut editor,
+                        changes,
+                        node,
+                        maybe_filter,
+                        Some(document_extents),
+                    ) {
+                        *text_changed = true;
+                        moved_from.push((source, text));
+                    }
+                }
                 TextInputAction::Edit(text_input_edit) => {
-                    apply_text_input_edit(
+                    *text_changed |= apply_edit(
                         text_input_edit,
                         &mut editor,
                         changes,
-                        node.max_chars,
+                        node,
                         maybe_filter,
+                        Some(document_extents),
                     );
                 }
             }
         }
     }
+
+    // Text moved into another input is deleted from its source once it's been inserted,
+    // unless the source's selection has changed since it was dropped
+    for (source, text) in moved_from {
+        let Ok((_, node, mut buffer, _, maybe_filter, _)) = query.get_mut(source) else {
+            continue;
+        };
+        let TextInputBuffer {
+            editor,
+            changes,
+            text_changed,
+            document_extents,
+            ..
+        } = &mut *buffer;
+        let mut editor = editor.borrow_with(font_system);
+        if editor
+            .copy_selection()
+            .is_some_and(|selected| selected == text)
+        {
+            *text_changed |= apply_edit(
+                TextInputEdit::Delete,
+                &mut editor,
+                changes,
+                node,
+                maybe_filter,
+                Some(document_extents),
+            );
+        }
+    }
+}
+
+/// Consumes the keyboard input received by the focused text input, see [`TextInputKeyboardConsumption`]
+pub fn consume_text_input_keyboard_input(
+    consumption: Res<TextInputKeyboardConsumption>,
+    input_focus: Res<InputFocus>,
+    query: Query<(), With<TextInputNode>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut keyboard_inputs: MessageMutator<KeyboardInput>,
+) {
+    let is_focused = input_focus
+        .get()
+        .is_some_and(|entity| query.contains(entity));
+
+    for keyboard_input in keyboard_inputs.read() {
+        if !is_focused || !consumption.consumes(keyboard_input.key_code) {
+            continue;
+        }
+
+        // Only keys received this frame are reset, held keys are reset again by their repeats
+        keys.reset(keyboard_input.key_code);
+        keyboard_input.key_code = KeyCode::Unidentified(NativeKeyCode::Unidentified);
+        keyboard_input.logical_key = Key::Unidentified(NativeKey::Unidentified);
+        keyboard_input.text = None;
+    }
 }
 
 pub fn on_focused_keyboard_input(
-    trigger: On<FocusedInput<KeyboardInput>>,
-    mut query: Query<(&TextInputNode, &mut TextInputQueue)>,
+    mut trigger: On<FocusedInput<KeyboardInput>>,
+    mut query: Query<(&TextInputNode, &mut TextInputQueue, &mut TextInputEditMode)>,
     mut global_state: ResMut<TextInputGlobalState>,
+    mut mode_changed_writer: MessageWriter<TextInputEditModeChanged>,
 ) {
-    if let Ok((input, mut queue)) = query.get_mut(trigger.focused_entity) {
-        let TextInputGlobalState {
-            shift,
-            overwrite_mode,
-            command,
-        } = &mut *global_state;
+    if let Ok((input, mut queue, mut edit_mode)) = query.get_mut(trigger.focused_entity) {
+        let mut overwrite_mode = *edit_mode == TextInputEditMode::Overwrite;
         queue_text_input_action(
-            &input.mode,
-            shift,
-            overwrite_mode,
-            command,
+            input,
+            &mut global_state,
+            &mut overwrite_mode,
             &trigger.event().input,
             |action| {
                 queue.add(action);
             },
         );
+
+        let mode = if overwrite_mode {
+            TextInputEditMode::Overwrite
+        } else {
+            TextInputEditMode::Insert
+        };
+        if edit_mode.set_if_neq(mode) {
+            mode_changed_writer.write(TextInputEditModeChanged {
+                entity: trigger.focused_entity,
+                mode,
+            });
+        }
+
+        // Tab indents editable multi-line inputs. Otherwise, and always with Ctrl held, the event
+        // propagates so `TabNavigationPlugin` can move the focus to the next input or widget.
+        if trigger.event().input.logical_key == Key::Tab
+            && matches!(input.mode, TextInputMode::MultiLine { .. })
+            && !input.read_only
+            && !global_state.command
+        {
+            trigger.propagate(false);
+        }
     }
 }
@@ -1,69 +1,150 @@
 pub mod actions;
 pub mod clipboard;
+pub mod context_menu;
+pub mod drag_and_drop;
 pub mod edit;
 pub mod render;
+pub mod style;
 pub mod text_input_pipeline;
+pub mod theme;
 
+use std::borrow::Cow;
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 use actions::TextInputAction;
-use bevy::app::{Plugin, PostUpdate};
-use bevy::asset::AssetEventSystems;
+use bevy::app::{Plugin, PostUpdate, PreUpdate};
+use bevy::asset::{AssetApp, AssetEventSystems};
 use bevy::color::Color;
 use bevy::color::palettes::css::SKY_BLUE;
 use bevy::color::palettes::tailwind::GRAY_400;
+use bevy::color::palettes::tailwind::GRAY_600;
+use bevy::ecs::change_detection::{DetectChanges, DetectChangesMut};
 use bevy::ecs::component::Component;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::lifecycle::HookContext;
 use bevy::ecs::message::Message;
 use bevy::ecs::observer::Observer;
-use bevy::ecs::query::Changed;
+use bevy::ecs::query::With;
 use bevy::ecs::resource::Resource;
 use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::schedule::common_conditions::resource_exists;
 use bevy::ecs::system::Query;
+use bevy::ecs::system::Res;
 use bevy::ecs::world::DeferredWorld;
+use bevy::input::keyboard::KeyCode;
 use bevy::input_focus::InputFocus;
+use bevy::input_focus::InputFocusSystems;
+use bevy::input_focus::tab_navigation::TabIndex;
+use bevy::log::warn;
 use bevy::math::{Rect, Vec2};
+use bevy::platform::collections::HashMap;
 use bevy::prelude::ReflectComponent;
-use bevy::reflect::{Reflect, std_traits::ReflectDefault};
+use bevy::prelude::ReflectResource;
+use bevy::reflect::{Reflect, reflect_remote, std_traits::ReflectDefault};
 use bevy::render::{ExtractSchedule, RenderApp};
-use bevy::text::{GlyphAtlasInfo, TextFont};
+use bevy::text::{GlyphAtlasInfo, TextError, TextFont};
 use bevy::text::{Justify, TextColor};
-use bevy::ui::{Node, UiSystems};
+use bevy::ui::{ContentSize, Node, UiSystems};
 use bevy::ui_render::{RenderUiSystems, extract_text_sections};
-use cosmic_text::{Buffer, Change, Edit, Editor, Metrics, Wrap};
+use context_menu::{
+    TextInputContextMenu, TextInputContextMenuSelected, on_click_text_input_context_menu,
+    on_press_text_input_context_menu, text_input_context_menu_system,
+};
+use cosmic_text::{
+    Align, Attrs, AttrsList, Buffer, BufferLine, Change, Cursor, Edit, Editor, FontSystem,
+    LineEnding, Metrics, Scroll, Selection, Shaping, Wrap,
+};
+use drag_and_drop::{
+    on_click_text_input_selection, on_drag_drop_text_input, on_drag_leave_text_input,
+    on_drag_over_text_input, text_input_file_drop_system,
+};
 use edit::{
-    cursor_blink_system, mouse_wheel_scroll, on_drag_text_input, on_focused_keyboard_input,
-    on_move_clear_multi_click, on_multi_click_set_selection, on_text_input_pressed,
-    process_text_input_queues,
+    auto_scroll_dragged_text_inputs, consume_text_input_keyboard_input, cursor_blink_system,
+    mouse_wheel_scroll, on_drag_end_text_input, on_drag_text_input, on_focused_keyboard_input,
+    on_text_input_pressed, process_text_input_queues, release_blurred_text_inputs,
+    release_idle_text_inputs, restore_released_text_inputs, text_input_caret_system,
+    unfocus_on_click_outside,
 };
 use render::{extract_text_input_nodes, extract_text_input_prompts};
+use style::text_input_state_style_system;
 use text_input_pipeline::{
-    TextInputPipeline, remove_dropped_font_atlas_sets_from_text_input_pipeline,
-    text_input_prompt_system, text_input_system,
+    DocumentExtents, TextInputPipeline, remove_dropped_font_atlas_sets_from_text_input_pipeline,
+    text_input_measure_system, text_input_prompt_system, text_input_system,
 };
+use theme::{TextInputTheme, TextInputThemeFile, TextInputThemeLoader, apply_text_input_themes};
 
 pub struct TextInputPlugin;
 
 impl Plugin for TextInputPlugin {
     fn build(&self, app: &mut bevy::app::App) {
         app.add_message::<SubmitText>()
+            .add_message::<TextInputLayoutError>()
+            .add_message::<TextInputEditModeChanged>()
             .add_plugins(bevy::input_focus::InputDispatchPlugin)
             .init_resource::<TextInputGlobalState>()
             .init_resource::<TextInputPipeline>()
             .init_resource::<clipboard::Clipboard>()
+            .init_resource::<TextInputFilterRegistry>()
+            .init_resource::<TextInputClickSettings>()
+            .register_type::<TextInputClickSettings>()
+            .add_observer(unfocus_on_click_outside)
+            .add_observer(on_press_text_input_context_menu)
+            .add_observer(on_click_text_input_context_menu)
+            .add_message::<TextInputContextMenuSelected>()
+            .register_type::<TextInputContextMenu>()
+            .register_type::<TextInputKeyboardConsumption>()
+            .register_type::<TextInputNode>()
+            .register_type::<TextInputFilter>()
+            .register_type::<TextInputContents>()
+            .register_type::<TextInputQueue>()
+            .register_type::<TextInputPrompt>()
+            .register_type::<TextInputStyle>()
+            .register_type::<TextInputAutoSize>()
+            .register_type::<TextInputLargeDocument>()
+            .register_type::<TextInputFocusRing>()
+            .register_type::<TextInputLogView>()
+            .register_type::<TextInputStateStyle>()
+            .register_type::<TextInputInvalid>()
+            .register_type::<TextInputCursorBlink>()
+            .register_type::<TextInputCaret>()
+            .register_type::<TextInputEditMode>()
+            .register_type::<theme::TextInputThemeHandle>()
+            .init_asset::<TextInputTheme>()
+            .init_asset_loader::<TextInputThemeLoader>()
+            .register_type::<TextInputThemeFile>()
+            .add_systems(
+                PreUpdate,
+                consume_text_input_keyboard_input
+                    .run_if(resource_exists::<TextInputKeyboardConsumption>)
+                    .after(InputFocusSystems::Dispatch),
+            )
             .add_systems(
                 PostUpdate,
                 (
                     remove_dropped_font_atlas_sets_from_text_input_pipeline
                         .before(AssetEventSystems),
+                    (apply_text_input_themes, text_input_state_style_system)
+                        .chain()
+                        .in_set(UiSystems::Prepare),
+                    text_input_context_menu_system.in_set(UiSystems::Prepare),
+                    restore_released_text_inputs
+                        .after(UiSystems::Propagate)
+                        .before(UiSystems::Content),
+                    text_input_measure_system.in_set(UiSystems::Content),
                     (
+                        resolve_named_text_input_filters,
                         cursor_blink_system,
                         mouse_wheel_scroll,
+                        auto_scroll_dragged_text_inputs,
+                        text_input_file_drop_system,
                         process_text_input_queues,
+                        release_blurred_text_inputs,
                         update_text_input_contents,
                         text_input_system,
+                        text_input_caret_system,
                         text_input_prompt_system,
+                        release_idle_text_inputs,
                     )
                         .chain()
                         .in_set(UiSystems::PostLayout),
@@ -84,15 +165,21 @@ impl Plugin for TextInputPlugin {
     }
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Default, Debug)]
 #[require(
     Node,
+    ContentSize,
     TextInputBuffer,
     TextFont,
     TextInputLayoutInfo,
     TextInputStyle,
     TextColor,
-    TextInputQueue
+    TextInputQueue,
+    TextInputCursorBlink,
+    TextInputCaret,
+    TextInputEditMode,
+    TabIndex
 )]
 #[component(
     on_add = on_add_textinputnode,
@@ -106,16 +193,43 @@ pub struct TextInputNode {
     pub mode: TextInputMode,
     /// Maximum number of characters that can entered into the input buffer
     pub max_chars: Option<usize>,
-    /// Should overwrite mode be available
+    /// Should overwrite mode be available, toggled by the Insert key
     pub allow_overwrite_mode: bool,
     /// Can the text input be activated
     pub is_enabled: bool,
+    /// The text can be focused, navigated, selected and copied, but not edited
+    pub read_only: bool,
     /// Activate on pointer down
     pub focus_on_pointer_down: bool,
     /// Deactivate after text submitted
     pub unfocus_on_submit: bool,
+    /// Deactivate when the pointer is pressed anywhere outside of the input, on other UI nodes or empty space
+    pub unfocus_on_click_outside: bool,
+    /// What happens to the selection when the input loses focus
+    pub selection_on_blur: SelectionOnBlur,
     /// Text justification
     pub justification: Justify,
+    /// Maximum number of lines (separated by newlines) in multi-line mode
+    pub max_lines: Option<usize>,
+    /// Maximum number of visual lines, after wrapping, in multi-line mode
+    pub max_visual_lines: Option<usize>,
+    /// In multi-line mode, submit on Enter and create a new line on Shift-Enter instead
+    pub submit_on_enter: bool,
+    /// How newlines in pasted text are handled
+    pub paste_newlines: NewlinePolicy,
+    /// Intrinsic width of the input in characters, used when its width isn't set by `Node`
+    pub size_in_chars: usize,
+    /// Release the input's `TextInputBuffer` while it is unfocused and idle, replacing it with a [`TextInputReleasedText`]
+    /// holding only its text, and discard its undo history when it loses focus.
+    /// Reduces the memory and per-frame cost of forms with many inputs, at the cost of rebuilding and reshaping the text
+    /// when the input is next focused, hovered or edited.
+    /// Inputs showing a selection or an unfocused caret keep their buffers, but their shaping caches are freed while unfocused.
+    pub release_on_blur: bool,
+    /// Selected text can be dragged to move it within the input or into other inputs, and text dragged from other inputs can be dropped into it.
+    /// Holding Ctrl or Command when dropping copies the text instead.
+    pub allow_drag_and_drop: bool,
+    /// What is inserted when files are dropped onto the input from the operating system
+    pub file_drop: FileDropPolicy,
 }
 
 impl Default for TextInputNode {
@@ -126,9 +240,20 @@ impl Default for TextInputNode {
             max_chars: None,
             allow_overwrite_mode: true,
             is_enabled: true,
+            read_only: false,
             focus_on_pointer_down: true,
-            unfocus_on_submit: true,
+            unfocus_on_submit: false,
+            unfocus_on_click_outside: false,
+            selection_on_blur: SelectionOnBlur::default(),
             justification: Justify::Left,
+            max_lines: None,
+            max_visual_lines: None,
+            submit_on_enter: false,
+            paste_newlines: NewlinePolicy::default(),
+            size_in_chars: 20,
+            release_on_blur: false,
+            allow_drag_and_drop: true,
+            file_drop: FileDropPolicy::default(),
         }
     }
 }
@@ -136,9 +261,12 @@ impl Default for TextInputNode {
 fn on_add_textinputnode(mut world: DeferredWorld, context: HookContext) {
     for mut observer in [
         Observer::new(on_drag_text_input),
+        Observer::new(on_drag_end_text_input),
+        Observer::new(on_drag_over_text_input),
+        Observer::new(on_drag_leave_text_input),
+        Observer::new(on_drag_drop_text_input),
+        Observer::new(on_click_text_input_selection),
         Observer::new(on_text_input_pressed),
-        Observer::new(on_multi_click_set_selection),
-        Observer::new(on_move_clear_multi_click),
         Observer::new(on_focused_keyboard_input),
     ] {
         observer.watch_entity(context.entity);
@@ -165,21 +293,127 @@ pub struct SubmitText {
     pub text: String,
 }
 
-/// Mode of text input
+/// Sent when a text input's text or prompt fails to be laid out.
+/// The layout is retried each frame, but the error is only sent again after a successful layout.
+#[derive(Message, Debug)]
+pub struct TextInputLayoutError {
+    /// The text input entity
+    pub entity: Entity,
+    /// The error
+    pub error: TextError,
+}
+
+/// Sent when a text input's [`TextInputEditMode`] is toggled by the Insert key
+#[derive(Message, Debug)]
+pub struct TextInputEditModeChanged {
+    /// The text input entity
+    pub entity: Entity,
+    /// The input's new edit mode
+    pub mode: TextInputEditMode,
+}
+
+/// Reflection for cosmic-text's `Wrap`
+#[reflect_remote(Wrap)]
 #[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WrapReflect {
+    None,
+    Glyph,
+    Word,
+    WordOrGlyph,
+}
+
+/// Mode of text input
+#[derive(Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
 pub enum TextInputMode {
     /// Scrolling text input
-    /// Submit on shift-enter
-    MultiLine { wrap: Wrap },
+    /// Submit on shift-enter, or on enter if `submit_on_enter` is set
+    MultiLine {
+        #[reflect(remote = WrapReflect)]
+        wrap: Wrap,
+    },
     /// Single line text input
     /// Scrolls horizontally
     /// Submit on enter
     SingleLine,
 }
 
+/// What happens to a text input's selection when it loses focus
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum SelectionOnBlur {
+    /// The selection is kept, and drawn with `TextInputStyle::inactive_selection_color` while the input is unfocused
+    #[default]
+    Keep,
+    /// The selection is cleared
+    Clear,
+}
+
+/// What is inserted into a text input when a file is dragged from the operating system and dropped onto it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum FileDropPolicy {
+    /// Dropped files are ignored
+    Ignore,
+    /// The file's path is inserted
+    #[default]
+    Path,
+    /// The file's text is inserted. Files larger than `max_bytes` or that aren't valid UTF-8 are ignored.
+    /// Files are read on the `IoTaskPool`, so the text is inserted once the read completes.
+    Contents { max_bytes: u64 },
+}
+
+/// How newlines in pasted text are handled
+#[derive(Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum NewlinePolicy {
+    /// Newlines are inserted unchanged.
+    /// Single-line inputs always collapse newlines.
+    #[default]
+    Keep,
+    /// Each run of newlines is replaced with a single space
+    Collapse,
+    /// Text containing newlines is rejected
+    Reject,
+}
+
+impl NewlinePolicy {
+    /// Apply the policy to `text`, returns `None` if the text is rejected
+    pub fn apply<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
+        if !text.contains(['\n', '\r']) {
+            return Some(Cow::Borrowed(text));
+        }
+
+        match self {
+            NewlinePolicy::Keep => Some(Cow::Borrowed(text)),
+            NewlinePolicy::Collapse => {
+                let mut out = String::with_capacity(text.len());
+                let mut in_break = false;
+                for c in text.chars() {
+                    if c == '\n' || c == '\r' {
+                        if !in_break {
+                            out.push(' ');
+                            in_break = true;
+                        }
+                    } else {
+                        out.push(c);
+                        in_break = false;
+                    }
+                }
+                Some(Cow::Owned(out))
+            }
+            NewlinePolicy::Reject => None,
+        }
+    }
+}
+
+/// A custom filter function
+pub type TextInputFilterFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
 /// Any actions that modify a text input's text so that it fails
 /// to pass the filter are not applied.
-#[derive(Component)]
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component, Debug)]
 pub enum TextInputFilter {
     /// Positive integer input
     /// accepts only digits
@@ -196,8 +430,16 @@ pub enum TextInputFilter {
     /// Alphanumeric input
     /// accepts only `0-9`, `a-z` and `A-Z`
     Alphanumeric,
-    /// Custom filter
-    Custom(Box<dyn Fn(&str) -> bool + Send + Sync>),
+    /// Custom filter.
+    /// Named custom filters are reflectable and are looked up in the `TextInputFilterRegistry`
+    /// when the filter is added or the registry changes. Unresolved custom filters accept any text.
+    Custom {
+        /// Name of the filter in the `TextInputFilterRegistry`, empty for unregistered filters
+        name: String,
+        /// The filter function, not reflected
+        #[reflect(ignore)]
+        filter: Option<TextInputFilterFn>,
+    },
 }
 
 impl core::fmt::Debug for TextInputFilter {
@@ -208,7 +450,7 @@ impl core::fmt::Debug for TextInputFilter {
             Self::Decimal => f.write_str("Decimal"),
             Self::Hex => f.write_str("Hex"),
             Self::Alphanumeric => f.write_str("Alphanumeric"),
-            Self::Custom(_) => f.write_str("Custom"),
+            Self::Custom { name, .. } => f.debug_struct("Custom").field("name", name).finish(),
         }
     }
 }
@@ -217,7 +459,7 @@ impl TextInputFilter {
     /// Returns true if the text passes the filter
     pub fn is_match(&self, text: &str) -> bool {
         // Always passes if the input is empty unless using a custom filter
-        if text.is_empty() && !matches!(self, Self::Custom(_)) {
+        if text.is_empty() && !matches!(self, Self::Custom { .. }) {
             return true;
         }
 
@@ -240,13 +482,74 @@ impl TextInputFilter {
                 .is_ok(),
             TextInputFilter::Hex => text.chars().all(|c| c.is_ascii_hexdigit()),
             TextInputFilter::Alphanumeric => text.chars().all(|c| c.is_ascii_alphanumeric()),
-            TextInputFilter::Custom(is_match) => is_match(text),
+            TextInputFilter::Custom { filter, .. } => {
+                filter.as_ref().is_none_or(|filter| filter(text))
+            }
         }
     }
 
     /// Create a custom filter
     pub fn custom(filter_fn: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
-        Self::Custom(Box::new(filter_fn))
+        Self::Custom {
+            name: String::new(),
+            filter: Some(Arc::new(filter_fn)),
+        }
+    }
+
+    /// Create a custom filter using the function registered under `name` in the `TextInputFilterRegistry`
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::Custom {
+            name: name.into(),
+            filter: None,
+        }
+    }
+}
+
+/// Custom filter functions that can be referred to by name with [`TextInputFilter::named`]
+/// Because only the name of a custom filter is reflected, custom filters in scenes must be registered here.
+#[derive(Resource, Default)]
+pub struct TextInputFilterRegistry {
+    filters: HashMap<String, TextInputFilterFn>,
+}
+
+impl TextInputFilterRegistry {
+    /// Register a custom filter function under `name`, replacing any previous filter with the same name
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        filter_fn: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) {
+        self.filters.insert(name.into(), Arc::new(filter_fn));
+    }
+
+    /// Get the filter function registered under `name`
+    pub fn get(&self, name: &str) -> Option<&TextInputFilterFn> {
+        self.filters.get(name)
+    }
+}
+
+/// Looks up the functions of named custom filters in the `TextInputFilterRegistry`
+pub fn resolve_named_text_input_filters(
+    registry: Res<TextInputFilterRegistry>,
+    mut query: Query<(Entity, &mut TextInputFilter)>,
+) {
+    for (entity, mut filter) in query.iter_mut() {
+        if !(filter.is_changed() || registry.is_changed()) {
+            continue;
+        }
+
+        let TextInputFilter::Custom { name, filter } = filter.bypass_change_detection() else {
+            continue;
+        };
+
+        if name.is_empty() {
+            continue;
+        }
+
+        *filter = registry.get(name).cloned();
+        if filter.is_none() {
+            warn!("No custom text input filter named \"{name}\" is registered, used by {entity}.");
+        }
     }
 }
 
@@ -269,18 +572,84 @@ impl TextInputMode {
 
 #[derive(Component, Debug)]
 pub struct TextInputBuffer {
+    /// The input's text editor.
+    /// After changing the text through the editor directly, call [`TextInputBuffer::mark_text_changed`] so that `TextInputContents` is updated.
     pub editor: Editor<'static>,
     pub(crate) selection_rects: Vec<Rect>,
-    pub(crate) cursor_blink_time: f32,
     pub(crate) needs_update: bool,
+    /// Set when an edit changes the text, cleared when `TextInputContents` is updated
+    pub(crate) text_changed: bool,
     pub(crate) prompt_buffer: Option<Buffer>,
     pub(crate) changes: cosmic_undo_2::Commands<Change>,
+    pub(crate) measured_size: Option<Vec2>,
+    /// Glyphs of the visible layout runs, keyed by line id and the byte index of the run's first glyph
+    pub(crate) glyph_cache: HashMap<(usize, usize), Vec<TextInputGlyph>>,
+    /// Sizes of the lines of a large document
+    pub(crate) document_extents: DocumentExtents,
+    /// Next id to assign to a buffer line's metadata
+    pub(crate) next_line_id: usize,
+    /// Set when a layout error has been reported, cleared once the text is laid out successfully
+    pub(crate) layout_error_reported: bool,
+    /// Set when a prompt layout error has been reported, cleared once the prompt is laid out successfully
+    pub(crate) prompt_layout_error_reported: bool,
 }
 
 impl TextInputBuffer {
     pub fn get_text(&self) -> String {
         self.editor.with_buffer(get_text)
     }
+
+    /// True if there is a change that can be undone
+    pub fn can_undo(&self) -> bool {
+        self.changes.iter_realized().next().is_some()
+    }
+
+    /// True if there is an undone change that can be redone
+    pub fn can_redo(&self) -> bool {
+        self.changes.is_undoing()
+    }
+
+    /// Marks the text as changed, so that `TextInputContents` is updated.
+    /// Needed after editing the text through `editor` directly, edits from the `TextInputQueue` and `set_text` mark the text as changed themselves.
+    pub fn mark_text_changed(&mut self) {
+        self.text_changed = true;
+        self.document_extents.invalidate();
+    }
+
+    /// Replace the input's text, without applying its filter or limits.
+    /// Moves the cursor to the start of the text and discards the undo history.
+    pub fn set_text(&mut self, text: &str) {
+        self.editor.with_buffer_mut(|buffer| {
+            let (attrs_list, align) = buffer.lines.first().map_or_else(
+                || (AttrsList::new(&Attrs::new()), None),
+                |line| (line.attrs_list().clone(), line.align()),
+            );
+            buffer.lines = buffer_lines(text, &attrs_list, align);
+            buffer.set_scroll(Scroll::default());
+            buffer.set_redraw(true);
+        });
+        self.editor.set_cursor(Cursor::default());
+        self.editor.set_selection(Selection::None);
+        self.changes = Default::default();
+        self.document_extents.invalidate();
+        self.text_changed = true;
+    }
+}
+
+/// Splits `text` into unshaped buffer lines with the given attributes and alignment
+fn buffer_lines(text: &str, attrs_list: &AttrsList, align: Option<Align>) -> Vec<BufferLine> {
+    text.split('\n')
+        .map(|line| {
+            let mut buffer_line = BufferLine::new(
+                line.strip_suffix('\r').unwrap_or(line),
+                LineEnding::default(),
+                attrs_list.clone(),
+                Shaping::Advanced,
+            );
+            buffer_line.set_align(align);
+            buffer_line
+        })
+        .collect()
 }
 
 impl Default for TextInputBuffer {
@@ -288,10 +657,82 @@ impl Default for TextInputBuffer {
         Self {
             editor: Editor::new(Buffer::new_empty(Metrics::new(20.0, 20.0))),
             selection_rects: vec![],
-            cursor_blink_time: 0.,
             needs_update: true,
+            text_changed: false,
             prompt_buffer: None,
             changes: cosmic_undo_2::Commands::default(),
+            measured_size: None,
+            glyph_cache: HashMap::default(),
+            document_extents: DocumentExtents::default(),
+            next_line_id: 0,
+            layout_error_reported: false,
+            prompt_layout_error_reported: false,
+        }
+    }
+}
+
+/// Held by an idle unfocused input with `release_on_blur` set in place of its [`TextInputBuffer`], which is removed.
+/// Only the text, cursor and layout settings needed to restore the buffer are kept, the input is drawn from its `TextInputLayoutInfo`.
+/// The input's `TextInputBuffer` is restored when it's focused, hovered, has queued actions, or its font, node or settings change.
+#[derive(Component, Debug, Clone)]
+pub struct TextInputReleasedText {
+    pub(crate) text: String,
+    pub(crate) cursor: Cursor,
+    pub(crate) scroll: Scroll,
+    pub(crate) metrics: Metrics,
+    pub(crate) size: (Option<f32>, Option<f32>),
+    pub(crate) wrap: Wrap,
+    pub(crate) attrs_list: AttrsList,
+    pub(crate) align: Option<Align>,
+    pub(crate) measured_size: Option<Vec2>,
+}
+
+impl TextInputReleasedText {
+    /// Keeps the text, cursor and layout settings of `buffer`
+    pub(crate) fn new(buffer: &TextInputBuffer) -> Self {
+        buffer.editor.with_buffer(|cosmic_buffer| {
+            let (attrs_list, align) = cosmic_buffer.lines.first().map_or_else(
+                || (AttrsList::new(&Attrs::new()), None),
+                |line| (line.attrs_list().clone(), line.align()),
+            );
+            Self {
+                text: cosmic_buffer
+                    .lines
+                    .iter()
+                    .map(BufferLine::text)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                cursor: buffer.editor.cursor(),
+                scroll: cosmic_buffer.scroll(),
+                metrics: cosmic_buffer.metrics(),
+                size: cosmic_buffer.size(),
+                wrap: cosmic_buffer.wrap(),
+                attrs_list,
+                align,
+                measured_size: buffer.measured_size,
+            }
+        })
+    }
+
+    /// The input's text
+    pub fn get(&self) -> &str {
+        &self.text
+    }
+
+    /// Rebuilds the input's buffer, with its lines left unshaped until they're needed
+    pub(crate) fn restore(&self, font_system: &mut FontSystem) -> TextInputBuffer {
+        let mut buffer = Buffer::new_empty(self.metrics);
+        buffer.set_size(font_system, self.size.0, self.size.1);
+        buffer.set_wrap(font_system, self.wrap);
+        buffer.lines = buffer_lines(&self.text, &self.attrs_list, self.align);
+        buffer.set_scroll(self.scroll);
+        buffer.set_redraw(true);
+        let mut editor = Editor::new(buffer);
+        editor.set_cursor(self.cursor);
+        TextInputBuffer {
+            editor,
+            measured_size: self.measured_size,
+            ..Default::default()
         }
     }
 }
@@ -331,6 +772,103 @@ impl Default for TextInputPrompt {
     }
 }
 
+/// Sizes a text input to fit its contents.
+/// Multi-line inputs grow vertically between `min_lines` and `max_lines`, single-line inputs grow horizontally between `min_width` and `max_width`.
+/// Once the maximum is reached the input scrolls.
+/// The auto-sized axis's `Node` size should be left as `Val::Auto`.
+/// Optional component.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputAutoSize {
+    /// Minimum height in lines of a multi-line input
+    pub min_lines: usize,
+    /// Maximum height in lines of a multi-line input
+    pub max_lines: Option<usize>,
+    /// Minimum width in logical pixels of a single-line input
+    pub min_width: f32,
+    /// Maximum width in logical pixels of a single-line input
+    pub max_width: Option<f32>,
+}
+
+impl Default for TextInputAutoSize {
+    fn default() -> Self {
+        Self {
+            min_lines: 1,
+            max_lines: None,
+            min_width: 0.,
+            max_width: None,
+        }
+    }
+}
+
+/// Configures a multi-line input as an append-only log view, typically combined with `TextInputNode::read_only`.
+/// Lines are added with [`TextInputQueue::append`].
+/// Optional component.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputLogView {
+    /// Maximum number of lines retained, the oldest lines are removed once it is exceeded
+    pub max_lines: Option<usize>,
+    /// Keep the view scrolled to the bottom as lines are appended, unless it has been scrolled up
+    pub auto_scroll: bool,
+}
+
+impl Default for TextInputLogView {
+    fn default() -> Self {
+        Self {
+            max_lines: Some(1000),
+            auto_scroll: true,
+        }
+    }
+}
+
+/// Large-document mode for inputs holding very long texts.
+/// The lines within `overscan_lines` lines of the viewport are laid out and have their glyphs generated ahead of time, so they are ready when scrolled into view.
+/// `TextInputLayoutInfo::size` is the size of the whole document, with lines that haven't been laid out yet counted as a single empty line.
+/// Optional component.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputLargeDocument {
+    /// Number of lines above and below the viewport to lay out in advance
+    pub overscan_lines: usize,
+    /// If false, the input's `TextInputContents` isn't updated while the input is focused, only once it loses focus,
+    /// so the whole text isn't copied after every edit. Use `TextInputBuffer::get_text` to read the text while focused.
+    pub sync_contents_while_focused: bool,
+}
+
+impl Default for TextInputLargeDocument {
+    fn default() -> Self {
+        Self {
+            overscan_lines: 10,
+            sync_contents_while_focused: false,
+        }
+    }
+}
+
+/// Ring drawn around a text input while it is focused and `InputFocusVisible` is set,
+/// which `TabNavigationPlugin` does when focus is moved with the keyboard.
+/// Optional component.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputFocusRing {
+    /// Color of the ring
+    pub color: Color,
+    /// Thickness of the ring in logical pixels
+    pub width: f32,
+    /// Gap between the ring and the edge of the input in logical pixels
+    pub offset: f32,
+}
+
+impl Default for TextInputFocusRing {
+    fn default() -> Self {
+        Self {
+            color: SKY_BLUE.into(),
+            width: 2.,
+            offset: 2.,
+        }
+    }
+}
+
 /// Styling for a text cursor
 #[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
 #[reflect(Component, Default, Debug, PartialEq)]
@@ -339,6 +877,8 @@ pub struct TextInputStyle {
     pub cursor_color: Color,
     /// Selection color
     pub selection_color: Color,
+    /// Selection color while the input is unfocused, if unset uses `selection_color`
+    pub inactive_selection_color: Option<Color>,
     /// Selected text tint, if unset uses the `TextColor`
     pub selected_text_color: Option<Color>,
     /// Width of the cursor
@@ -349,6 +889,31 @@ pub struct TextInputStyle {
     pub cursor_height: f32,
     /// Time cursor blinks in seconds
     pub blink_interval: f32,
+    /// Fade the cursor in and out as it blinks, instead of switching it on and off
+    pub fade_blink: bool,
+    /// Shape of the cursor
+    pub caret_shape: CaretShape,
+    /// Shape of the cursor in overwrite mode
+    pub overwrite_caret_shape: CaretShape,
+    /// Shape of the cursor while the input is unfocused, if `None` the cursor is hidden
+    pub unfocused_caret_shape: Option<CaretShape>,
+    /// Approximate time in seconds the cursor takes to move to a new position, if zero the cursor moves instantly
+    pub caret_motion_time: f32,
+}
+
+/// Shape of a text input's cursor
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum CaretShape {
+    /// Vertical bar before the next glyph, `cursor_width` wide
+    #[default]
+    Bar,
+    /// Block covering the next glyph
+    Block,
+    /// Line under the next glyph, `cursor_width` thick
+    Underline,
+    /// Outline of a block covering the next glyph, `cursor_width` thick
+    HollowBlock,
 }
 
 impl Default for TextInputStyle {
@@ -356,15 +921,152 @@ impl Default for TextInputStyle {
         Self {
             cursor_color: GRAY_400.into(),
             selection_color: SKY_BLUE.into(),
+            inactive_selection_color: Some(GRAY_600.into()),
             selected_text_color: None,
             cursor_width: 3.,
             cursor_radius: 0.,
             cursor_height: 1.,
             blink_interval: 0.5,
+            fade_blink: false,
+            caret_shape: CaretShape::Bar,
+            overwrite_caret_shape: CaretShape::Block,
+            unfocused_caret_shape: None,
+            caret_motion_time: 0.,
         }
     }
 }
 
+/// Colors for one state of a text input, used by [`TextInputStateStyle`].
+/// `None` fields are left unchanged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub struct TextInputColors {
+    /// Sets the input's `BackgroundColor`
+    pub background: Option<Color>,
+    /// Sets the input's `BorderColor`
+    pub border: Option<Color>,
+    /// Sets the color of the input's `Outline`, if it has one
+    pub outline: Option<Color>,
+    /// Sets the input's `TextColor`
+    pub text: Option<Color>,
+    /// Sets `TextInputStyle::cursor_color`
+    pub cursor: Option<Color>,
+    /// Sets `TextInputStyle::selection_color`
+    pub selection: Option<Color>,
+    /// Sets `TextInputStyle::selected_text_color`
+    pub selected_text: Option<Color>,
+}
+
+impl TextInputColors {
+    /// These colors, with any colors set in `other` taking precedence
+    pub fn with_overrides(self, other: &Self) -> Self {
+        Self {
+            background: other.background.or(self.background),
+            border: other.border.or(self.border),
+            outline: other.outline.or(self.outline),
+            text: other.text.or(self.text),
+            cursor: other.cursor.or(self.cursor),
+            selection: other.selection.or(self.selection),
+            selected_text: other.selected_text.or(self.selected_text),
+        }
+    }
+}
+
+/// State-dependent colors for a text input, applied automatically as the input is hovered, focused, disabled, etc.
+/// The colors of the input's current states are layered in the order normal, read-only, hovered, focused, invalid,
+/// with later states overriding earlier ones. Disabled inputs use the normal colors overridden by the disabled colors only.
+/// Any color set in some state should also be set in `normal`, otherwise the input keeps that color after leaving the state.
+/// Optional component.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputStateStyle {
+    pub normal: TextInputColors,
+    pub hovered: TextInputColors,
+    pub focused: TextInputColors,
+    /// Used when `TextInputNode::is_enabled` is false
+    pub disabled: TextInputColors,
+    /// Used when `TextInputNode::read_only` is true
+    pub read_only: TextInputColors,
+    /// Used when the input has a `TextInputInvalid` component
+    pub invalid: TextInputColors,
+}
+
+impl TextInputStateStyle {
+    /// Resolve the colors for an input in the given states
+    pub fn resolve(
+        &self,
+        input: &TextInputNode,
+        hovered: bool,
+        focused: bool,
+        invalid: bool,
+    ) -> TextInputColors {
+        if !input.is_enabled {
+            return self.normal.with_overrides(&self.disabled);
+        }
+
+        [
+            (input.read_only, &self.read_only),
+            (hovered, &self.hovered),
+            (focused, &self.focused),
+            (invalid, &self.invalid),
+        ]
+        .into_iter()
+        .filter(|(active, _)| *active)
+        .fold(self.normal, |colors, (_, overrides)| {
+            colors.with_overrides(overrides)
+        })
+    }
+}
+
+/// Marks a text input's contents as invalid, for styling with `TextInputStateStyle`
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputInvalid;
+
+/// Cursor blink timer, only advanced while the text input is focused
+#[derive(Component, Copy, Clone, Default, Debug, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct TextInputCursorBlink {
+    /// Time in seconds since the start of the current blink cycle
+    pub(crate) time: f32,
+}
+
+/// Position and size of a text input's cursor, updated by `text_input_caret_system`
+#[derive(Component, Copy, Clone, Default, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputCaret {
+    /// Position of the top left of the cursor in physical pixels relative to the text, eased towards the cursor's position in the text.
+    /// `None` if the cursor isn't displayed.
+    pub(crate) position: Option<Vec2>,
+    /// Width of the glyph following the cursor in physical pixels
+    pub(crate) glyph_width: f32,
+}
+
+/// Whether characters typed into a text input are inserted at the cursor, or overwrite the character following it.
+/// Toggled by the Insert key while the input is focused, if its `allow_overwrite_mode` is set.
+#[derive(Component, Copy, Clone, Default, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub enum TextInputEditMode {
+    #[default]
+    Insert,
+    Overwrite,
+}
+
+impl TextInputEditMode {
+    /// The other mode
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Insert => Self::Overwrite,
+            Self::Overwrite => Self::Insert,
+        }
+    }
+
+    /// True if the mode is `Overwrite` and the input allows overwrite mode
+    pub fn is_overwrite(self, input: &TextInputNode) -> bool {
+        self == Self::Overwrite && input.allow_overwrite_mode
+    }
+}
+
 fn get_text(buffer: &Buffer) -> String {
     buffer
         .lines
@@ -404,25 +1106,157 @@ pub struct TextInputGlyph {
     pub byte_length: usize,
 }
 
-#[derive(Default, Debug, Component, PartialEq)]
+/// Mirrors the text of a text input.
+/// If added with some text, e.g. when the input is loaded from a scene, the input's text is set to its contents.
+#[derive(Default, Debug, Component, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
 pub struct TextInputContents {
     text: String,
 }
 
 impl TextInputContents {
+    /// Contents to initialize a text input's text with
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+
     pub fn get(&self) -> &str {
         &self.text
     }
 }
 
+/// Updates `TextInputContents` after edits that change the text.
+/// Newly added non-empty `TextInputContents` set the input's text instead.
+/// The contents of focused large documents are only updated if their `sync_contents_while_focused` is set.
 pub fn update_text_input_contents(
-    mut query: Query<(&TextInputBuffer, &mut TextInputContents), Changed<TextInputBuffer>>,
+    input_focus: Res<InputFocus>,
+    mut query: Query<(
+        Entity,
+        &mut TextInputBuffer,
+        &mut TextInputContents,
+        Option<&TextInputLargeDocument>,
+    )>,
 ) {
-    for (buffer, mut contents) in query.iter_mut() {
+    for (entity, mut buffer, mut contents, large_document) in query.iter_mut() {
+        if contents.is_added() && !contents.text.is_empty() {
+            buffer.set_text(&contents.text);
+            continue;
+        }
+
+        if !(buffer.text_changed || buffer.is_added() || contents.is_added()) {
+            continue;
+        }
+
+        if large_document.is_some_and(|large_document| !large_document.sync_contents_while_focused)
+            && input_focus.get() == Some(entity)
+            && !contents.is_added()
+        {
+            continue;
+        }
+
         let text = buffer.get_text();
         if contents.text != text {
             contents.text = text;
         }
+
+        if buffer.text_changed {
+            buffer.text_changed = false;
+        }
+    }
+}
+
+/// Run condition that is true while a text input is focused.
+/// Use `not(text_input_focused())` to stop gameplay systems from reacting to keys typed into text inputs.
+pub fn text_input_focused()
+-> impl FnMut(Res<InputFocus>, Query<(), With<TextInputNode>>) -> bool + Clone {
+    |input_focus: Res<InputFocus>, query: Query<(), With<TextInputNode>>| {
+        input_focus
+            .get()
+            .is_some_and(|entity| query.contains(entity))
+    }
+}
+
+/// Insert this resource to consume keyboard input received by text inputs.
+/// While a text input is focused, after its keys have been dispatched to it, the keys it received that frame
+/// are reset in `ButtonInput<KeyCode>`, and their `KeyboardInput` messages are replaced with unidentified keys with no text,
+/// so that systems in `Update` don't see keys typed into the input.
+/// Keys in `passthrough_keys` and modifier keys are never consumed, so held modifiers such as Shift remain visible to
+/// tab navigation and other systems.
+#[derive(Resource, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Resource, Default, Debug, PartialEq)]
+pub struct TextInputKeyboardConsumption {
+    /// Keys that are passed through to the rest of the app while a text input is focused
+    pub passthrough_keys: Vec<KeyCode>,
+}
+
+impl TextInputKeyboardConsumption {
+    /// True if the key is consumed while a text input is focused
+    pub fn consumes(&self, key_code: KeyCode) -> bool {
+        !is_modifier_key(key_code) && !self.passthrough_keys.contains(&key_code)
+    }
+}
+
+/// Shift, Ctrl, Alt and Super keys, which are never consumed by text inputs
+pub fn is_modifier_key(key_code: KeyCode) -> bool {
+    matches!(
+        key_code,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+            | KeyCode::Meta
+            | KeyCode::Hyper
+            | KeyCode::Fn
+            | KeyCode::FnLock
+    )
+}
+
+impl Default for TextInputKeyboardConsumption {
+    fn default() -> Self {
+        Self {
+            passthrough_keys: vec![
+                KeyCode::Escape,
+                KeyCode::F1,
+                KeyCode::F2,
+                KeyCode::F3,
+                KeyCode::F4,
+                KeyCode::F5,
+                KeyCode::F6,
+                KeyCode::F7,
+                KeyCode::F8,
+                KeyCode::F9,
+                KeyCode::F10,
+                KeyCode::F11,
+                KeyCode::F12,
+            ],
+        }
+    }
+}
+
+/// Tolerances for detecting double, triple and quadruple clicks on text inputs
+#[derive(Resource, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Resource, Default, Debug, PartialEq)]
+pub struct TextInputClickSettings {
+    /// Maximum time in seconds between the presses of a multi-click
+    pub multi_click_time: f32,
+    /// Maximum distance in logical pixels between the presses of a multi-click
+    pub multi_click_distance: f32,
+    /// How fast an input scrolls while its selection is dragged past its edges.
+    /// Each second the input is scrolled by this multiple of the distance between the pointer and the input's edge.
+    pub drag_scroll_speed: f32,
+}
+
+impl Default for TextInputClickSettings {
+    fn default() -> Self {
+        Self {
+            multi_click_time: 0.5,
+            multi_click_distance: 4.,
+            drag_scroll_speed: 10.,
+        }
     }
 }
 
@@ -432,13 +1266,40 @@ pub struct TextInputGlobalState {
     pub shift: bool,
     /// Ctrl or Command key is held down
     pub command: bool,
-    /// If true typed glyphs overwrite the glyph at the current cursor position, instead of inserting before it.
-    pub overwrite_mode: bool,
+    /// Alt or Option key is held down
+    pub alt: bool,
+    /// AltGr key is held down
+    pub alt_graph: bool,
+    /// How editing shortcuts like Ctrl+C are matched to keys
+    pub shortcut_keys: ShortcutKeys,
+}
+
+/// How editing shortcuts like Ctrl+C are matched to keys
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum ShortcutKeys {
+    /// Match the letter of the logical key, like native text fields do, so shortcuts follow Latin layouts such as Dvorak.
+    /// Keys that don't type a Latin letter, on layouts such as Russian or Greek, fall back to their physical key.
+    #[default]
+    Logical,
+    /// Always match the physical key, by its position on a US keyboard
+    Physical,
+}
+
+impl TextInputGlobalState {
+    /// True if AltGr is held down. On Windows, AltGr is also reported as Ctrl+Alt, so outside of macOS
+    /// Ctrl+Alt is treated as AltGr, and typing with it inserts text instead of triggering shortcuts.
+    pub fn is_alt_graph(&self) -> bool {
+        self.alt_graph || (cfg!(not(target_os = "macos")) && self.command && self.alt)
+    }
 }
 
 /// Queued `TextInputActions` to be processed by `process_text_input_queues` and applied to the `TextInputBuffer`
-#[derive(Component, Default, Debug)]
+/// The queued actions aren't reflected.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component, Default, Debug)]
 pub struct TextInputQueue {
+    #[reflect(ignore)]
     pub actions: VecDeque<TextInputAction>,
 }
 
@@ -453,6 +1314,11 @@ impl TextInputQueue {
         self.actions.push_front(action);
     }
 
+    /// Queue lines of text to be appended to the end of the input
+    pub fn append(&mut self, text: impl Into<String>) {
+        self.add(TextInputAction::Append(text.into()));
+    }
+
     /// True if the queue is empty
     pub fn is_empty(&self) -> bool {
         self.actions.is_empty()
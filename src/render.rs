@@ -1,16 +1,25 @@
+use crate::CaretShape;
 use crate::TextInputBuffer;
+use crate::TextInputCaret;
+use crate::TextInputCursorBlink;
+use crate::TextInputEditMode;
+use crate::TextInputFocusRing;
 use crate::TextInputGlyph;
 use crate::TextInputLayoutInfo;
 use crate::TextInputNode;
 use crate::TextInputPrompt;
 use crate::TextInputPromptLayoutInfo;
+use crate::TextInputReleasedText;
 use crate::TextInputStyle;
+use crate::drag_and_drop::TextInputDropCaret;
 use crate::edit::is_buffer_empty;
 use bevy::asset::AssetId;
 use bevy::asset::Assets;
 use bevy::camera::visibility::InheritedVisibility;
 use bevy::color::Alpha;
 use bevy::color::LinearRgba;
+use bevy::color::Luminance;
+use bevy::color::Mix;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::system::Commands;
 use bevy::ecs::system::Query;
@@ -18,6 +27,7 @@ use bevy::ecs::system::Res;
 use bevy::ecs::system::ResMut;
 use bevy::image::TextureAtlasLayout;
 use bevy::input_focus::InputFocus;
+use bevy::input_focus::InputFocusVisible;
 use bevy::math::Affine2;
 use bevy::math::Rect;
 use bevy::math::Vec2;
@@ -36,14 +46,21 @@ use bevy::ui_render::ExtractedUiNode;
 use bevy::ui_render::ExtractedUiNodes;
 use bevy::ui_render::NodeType;
 use bevy::ui_render::UiCameraMap;
+use bevy::ui_render::shader_flags;
 use bevy::ui_render::stack_z_offsets;
 use cosmic_text::Edit;
+use std::f32::consts::PI;
+
+/// Selection rects are drawn beneath block carets, which are drawn beneath the text's glyphs
+const SELECTION_Z_OFFSET: f32 = stack_z_offsets::TEXT - 0.002;
+const BLOCK_CARET_Z_OFFSET: f32 = stack_z_offsets::TEXT - 0.001;
 
 pub fn extract_text_input_nodes(
     mut commands: Commands,
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
     texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
     active_text_input: Extract<Res<InputFocus>>,
+    focus_visible: Extract<Res<InputFocusVisible>>,
     uinode_query: Extract<
         Query<(
             Entity,
@@ -56,7 +73,11 @@ pub fn extract_text_input_nodes(
             &TextColor,
             &TextInputStyle,
             &TextInputNode,
-            &TextInputBuffer,
+            (Option<&TextInputBuffer>, Option<&TextInputReleasedText>),
+            &TextInputCursorBlink,
+            (&TextInputCaret, Option<&TextInputDropCaret>),
+            &TextInputEditMode,
+            Option<&TextInputFocusRing>,
         )>,
     >,
     camera_map: Extract<UiCameraMap>,
@@ -77,7 +98,11 @@ pub fn extract_text_input_nodes(
         text_color,
         style,
         input,
-        input_buffer,
+        (input_buffer, released),
+        cursor_blink,
+        (caret, drop_caret),
+        edit_mode,
+        focus_ring,
     ) in &uinode_query
     {
         // Skip if not visible or if size is set to zero (e.g. when a parent is set to `Display::None`)
@@ -89,15 +114,84 @@ pub fn extract_text_input_nodes(
             continue;
         };
 
+        let is_focused = active_text_input.0.is_some_and(|active| active == entity);
+
+        if is_focused
+            && focus_visible.0
+            && let Some(focus_ring) = focus_ring
+            && !focus_ring.color.is_fully_transparent()
+        {
+            let scale_factor = uinode.inverse_scale_factor().recip();
+            let width = focus_ring.width * scale_factor;
+            let outer_distance = width + focus_ring.offset * scale_factor;
+            let radius = uinode.border_radius();
+            let expand = |radius: f32| {
+                if 0. < radius {
+                    radius + outer_distance
+                } else {
+                    0.
+                }
+            };
+            extracted_uinodes.uinodes.push(ExtractedUiNode {
+                z_order: uinode.stack_index as f32 + stack_z_offsets::BORDER,
+                image: AssetId::default(),
+                clip: clip.map(|clip| clip.clip),
+                extracted_camera_entity,
+                transform: global_transform.into(),
+                item: ExtractedUiItem::Node {
+                    color: focus_ring.color.to_linear(),
+                    atlas_scaling: None,
+                    flip_x: false,
+                    flip_y: false,
+                    border_radius: ResolvedBorderRadius {
+                        top_left: expand(radius.top_left),
+                        top_right: expand(radius.top_right),
+                        bottom_right: expand(radius.bottom_right),
+                        bottom_left: expand(radius.bottom_left),
+                    },
+                    border: BorderRect::all(width),
+                    node_type: NodeType::Border(shader_flags::BORDER_ALL),
+                    rect: Rect {
+                        min: Vec2::ZERO,
+                        max: uinode.size() + 2. * outer_distance,
+                    },
+                },
+                main_entity: entity.into(),
+                render_entity: commands.spawn(TemporaryRenderEntity).id(),
+            });
+        }
+
         let color = text_color.0.to_linear();
         let selection_color = style
             .selected_text_color
             .map(|selection_color| selection_color.to_linear())
             .unwrap_or(color);
 
-        let scroll = input_buffer
-            .editor
-            .with_buffer(|buffer| Vec2::new(buffer.scroll().horizontal, 0.)); // buffer.scroll().vertical));
+        // Released inputs are drawn from their layout info, without a selection
+        let (scroll, line_height, cursor, selection, selection_rects) =
+            match (input_buffer, released) {
+                (Some(input_buffer), _) => {
+                    let (scroll, line_height) = input_buffer.editor.with_buffer(|buffer| {
+                        (buffer.scroll().horizontal, buffer.metrics().line_height)
+                    });
+                    (
+                        scroll,
+                        line_height,
+                        input_buffer.editor.cursor(),
+                        input_buffer.editor.selection_bounds(),
+                        input_buffer.selection_rects.as_slice(),
+                    )
+                }
+                (None, Some(released)) => (
+                    released.scroll.horizontal,
+                    released.metrics.line_height,
+                    released.cursor,
+                    None,
+                    [].as_slice(),
+                ),
+                (None, None) => continue,
+            };
+        let scroll = Vec2::new(scroll, 0.);
 
         let transform = Affine2::from(global_transform)
             * Affine2::from_translation(uinode.size() * -0.5 - scroll);
@@ -111,29 +205,38 @@ pub fn extract_text_input_nodes(
                 ),
         );
 
-        let clip = Some(
-            clip.map(|clip| clip.clip.intersect(node_rect))
-                .unwrap_or(node_rect),
+        let clip_rect = clip
+            .map(|clip| clip.clip.intersect(node_rect))
+            .unwrap_or(node_rect);
+        let clip = Some(clip_rect);
+
+        let glyph_scale = Vec2::new(
+            global_transform.matrix2.col(0).length(),
+            global_transform.matrix2.col(1).length(),
         );
 
-        let line_height = input_buffer
-            .editor
-            .with_buffer(|buffer| buffer.metrics().line_height);
+        let selection_rect_color = if is_focused {
+            style.selection_color
+        } else {
+            style
+                .inactive_selection_color
+                .unwrap_or(style.selection_color)
+        };
 
-        for (i, rect) in input_buffer.selection_rects.iter().enumerate() {
-            let size = if (1..input_buffer.selection_rects.len()).contains(&i) {
+        for (i, rect) in selection_rects.iter().enumerate() {
+            let size = if (1..selection_rects.len()).contains(&i) {
                 rect.size() + Vec2::Y
             } else {
                 rect.size()
             } + 2. * Vec2::X;
             extracted_uinodes.uinodes.push(ExtractedUiNode {
-                z_order: uinode.stack_index as f32 + stack_z_offsets::TEXT,
+                z_order: uinode.stack_index as f32 + SELECTION_Z_OFFSET,
                 image: AssetId::default(),
                 clip,
                 extracted_camera_entity,
                 transform: transform * Affine2::from_translation(rect.center()),
                 item: ExtractedUiItem::Node {
-                    color: LinearRgba::from(style.selection_color),
+                    color: LinearRgba::from(selection_rect_color),
                     atlas_scaling: None,
                     flip_x: false,
                     flip_y: false,
@@ -150,20 +253,117 @@ pub fn extract_text_input_nodes(
             });
         }
 
-        let cursor_visable = active_text_input.0.is_some_and(|active| active == entity)
-            && input.is_enabled
-            && input_buffer.cursor_blink_time < style.blink_interval
-            && !style.cursor_color.is_fully_transparent();
+        let caret_shape = if is_focused {
+            Some(if edit_mode.is_overwrite(input) {
+                style.overwrite_caret_shape
+            } else {
+                style.caret_shape
+            })
+        } else {
+            style.unfocused_caret_shape
+        };
 
-        let cursor_position = input_buffer
-            .editor
-            .cursor_position()
-            .filter(|_| cursor_visable);
+        // Unfocused cursors don't blink
+        let caret_alpha = if !is_focused {
+            1.
+        } else if style.fade_blink {
+            0.5 + 0.5 * (PI * cursor_blink.time / style.blink_interval).cos()
+        } else if cursor_blink.time < style.blink_interval {
+            1.
+        } else {
+            0.
+        };
 
-        let selection = input_buffer.editor.selection_bounds();
+        let caret_node = caret
+            .position
+            .zip(caret_shape)
+            .filter(|_| {
+                input.is_enabled && 0. < caret_alpha && !style.cursor_color.is_fully_transparent()
+            })
+            .map(|(position, shape)| {
+                let scale_factor = uinode.inverse_scale_factor().recip();
+                let thickness = style.cursor_width * scale_factor;
+                let cursor_height = line_height * style.cursor_height;
+
+                let (size, center_y, node_type, border) = match shape {
+                    CaretShape::Bar => (
+                        Vec2::new(thickness, cursor_height),
+                        0.5 * line_height,
+                        NodeType::Rect,
+                        BorderRect::ZERO,
+                    ),
+                    CaretShape::Block => (
+                        Vec2::new(caret.glyph_width, cursor_height),
+                        0.5 * line_height,
+                        NodeType::Rect,
+                        BorderRect::ZERO,
+                    ),
+                    CaretShape::Underline => (
+                        Vec2::new(caret.glyph_width, thickness),
+                        0.5 * (line_height + cursor_height - thickness),
+                        NodeType::Rect,
+                        BorderRect::ZERO,
+                    ),
+                    CaretShape::HollowBlock => (
+                        Vec2::new(caret.glyph_width, cursor_height),
+                        0.5 * line_height,
+                        NodeType::Border(shader_flags::BORDER_ALL),
+                        BorderRect::all(thickness),
+                    ),
+                };
+
+                let mut color = style.cursor_color.to_linear();
+                color.alpha *= caret_alpha;
+
+                let z_offset = if shape == CaretShape::Block {
+                    BLOCK_CARET_Z_OFFSET
+                } else {
+                    stack_z_offsets::TEXT
+                };
+
+                let node = ExtractedUiNode {
+                    z_order: uinode.stack_index as f32 + z_offset,
+                    image: AssetId::default(),
+                    clip,
+                    extracted_camera_entity,
+                    transform: transform
+                        * Affine2::from_translation(position + Vec2::new(0.5 * size.x, center_y)),
+                    item: ExtractedUiItem::Node {
+                        color,
+                        atlas_scaling: None,
+                        flip_x: false,
+                        flip_y: false,
+                        border_radius: ResolvedBorderRadius::ZERO,
+                        border,
+                        node_type,
+                        rect: Rect {
+                            min: Vec2::ZERO,
+                            max: size,
+                        },
+                    },
+                    main_entity: entity.into(),
+                    render_entity: commands.spawn(TemporaryRenderEntity).id(),
+                };
+                (shape, node)
+            });
+
+        // Block cursors are drawn beneath the text, and the glyph they cover is drawn in a color that contrasts with the cursor
+        let (caret_node, covered_glyph) = match caret_node {
+            Some((CaretShape::Block, node)) => {
+                extracted_uinodes.uinodes.push(node);
+                let contrast = if 0.5 < style.cursor_color.luminance() {
+                    LinearRgba::BLACK
+                } else {
+                    LinearRgba::WHITE
+                };
+                (None, Some((cursor.line, cursor.index, contrast)))
+            }
+            caret_node => (caret_node.map(|(_, node)| node), None),
+        };
 
         for TextInputGlyph {
             position,
+            size,
             atlas_info,
 
             line_index,
@@ -171,6 +371,13 @@ pub fn extract_text_input_nodes(
             ..
         } in text_layout_info.glyphs.iter()
         {
+            // Glyphs that are clipped entirely aren't extracted
+            let glyph_rect =
+                Rect::from_center_size(transform.transform_point2(*position), *size * glyph_scale);
+            if clip_rect.intersect(glyph_rect).is_empty() {
+                continue;
+            }
+
             let color_out = if let Some((s0, s1)) = selection {
                 if (s0.line < *line_index || (*line_index == s0.line && s0.index <= *byte_index))
                     && (*line_index < s1.line || (*line_index == s1.line && *byte_index < s1.index))
@@ -183,6 +390,13 @@ pub fn extract_text_input_nodes(
                 color
             };
 
+            let color_out = match covered_glyph {
+                Some((line, index, contrast)) if line == *line_index && index == *byte_index => {
+                    color_out.mix(&contrast, caret_alpha)
+                }
+                _ => color_out,
+            };
+
             let Some(rect) = texture_atlases
                 .get(atlas_info.texture_atlas)
                 .map(|atlas| atlas.textures[atlas_info.location.glyph_index].as_rect())
@@ -211,24 +425,27 @@ pub fn extract_text_input_nodes(
             end += 1;
         }
 
-        if let Some((x, y)) = cursor_position {
-            let cursor_height = line_height * style.cursor_height;
-
-            let x = x as f32;
-            let y = y as f32;
-
-            let scale_factor = uinode.inverse_scale_factor().recip();
-            let width = style.cursor_width * scale_factor;
+        if let Some(caret_node) = caret_node {
+            extracted_uinodes.uinodes.push(caret_node);
+        }
 
+        // Shows where text dragged over the input will be dropped
+        if let Some(drop_caret) = drop_caret
+            && input.is_enabled
+        {
+            let thickness = style.cursor_width * uinode.inverse_scale_factor().recip();
+            let size = Vec2::new(thickness, line_height * style.cursor_height);
             extracted_uinodes.uinodes.push(ExtractedUiNode {
                 z_order: uinode.stack_index as f32 + stack_z_offsets::TEXT,
                 image: AssetId::default(),
                 clip,
                 extracted_camera_entity,
                 transform: transform
-                    * Affine2::from_translation(Vec2::new(x + 0.5 * width, y + 0.5 * line_height)),
+                    * Affine2::from_translation(
+                        drop_caret.position + Vec2::new(0.5 * thickness, 0.5 * line_height),
+                    ),
                 item: ExtractedUiItem::Node {
-                    color,
+                    color: style.cursor_color.to_linear(),
                     atlas_scaling: None,
                     flip_x: false,
                     flip_y: false,
@@ -237,7 +454,7 @@ pub fn extract_text_input_nodes(
                     node_type: NodeType::Rect,
                     rect: Rect {
                         min: Vec2::ZERO,
-                        max: Vec2::new(width, cursor_height),
+                        max: size,
                     },
                 },
                 main_entity: entity.into(),
@@ -261,7 +478,7 @@ pub fn extract_text_input_prompts(
             &ComputedUiTargetCamera,
             &TextInputPromptLayoutInfo,
             &TextColor,
-            &TextInputBuffer,
+            (Option<&TextInputBuffer>, Option<&TextInputReleasedText>),
             &TextInputPrompt,
         )>,
     >,
@@ -281,12 +498,17 @@ pub fn extract_text_input_prompts(
         target,
         text_layout_info,
         text_color,
-        input,
+        (input_buffer, released),
         prompt,
     ) in &uinode_query
     {
         // only display the prompt if the text input is empty, including whitespace
-        if !input.editor.with_buffer(is_buffer_empty) {
+        let is_empty = match (input_buffer, released) {
+            (Some(input_buffer), _) => input_buffer.editor.with_buffer(is_buffer_empty),
+            (None, Some(released)) => released.text.is_empty(),
+            (None, None) => false,
+        };
+        if !is_empty {
             continue;
         }
 
@@ -0,0 +1,105 @@
+use crate::TextInputInvalid;
+use crate::TextInputNode;
+use crate::TextInputStateStyle;
+use crate::TextInputStyle;
+use bevy::ecs::change_detection::DetectChangesMut;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::query::Has;
+use bevy::ecs::system::Commands;
+use bevy::ecs::system::Query;
+use bevy::ecs::system::Res;
+use bevy::input_focus::InputFocus;
+use bevy::picking::hover::HoverMap;
+use bevy::platform::collections::HashSet;
+use bevy::text::TextColor;
+use bevy::ui::BackgroundColor;
+use bevy::ui::BorderColor;
+use bevy::ui::Outline;
+
+/// Applies the colors from each text input's `TextInputStateStyle` for its current state
+pub fn text_input_state_style_system(
+    mut commands: Commands,
+    input_focus: Res<InputFocus>,
+    hover_map: Res<HoverMap>,
+    mut query: Query<(
+        Entity,
+        &TextInputNode,
+        &TextInputStateStyle,
+        Has<TextInputInvalid>,
+        &mut TextInputStyle,
+        &mut TextColor,
+        Option<&mut BackgroundColor>,
+        Option<&mut BorderColor>,
+        Option<&mut Outline>,
+    )>,
+) {
+    let hovered_entities: HashSet<Entity> = hover_map
+        .values()
+        .flat_map(|hovers| hovers.keys().copied())
+        .collect();
+
+    for (
+        entity,
+        input,
+        state_style,
+        invalid,
+        mut style,
+        mut text_color,
+        background_color,
+        border_color,
+        outline,
+    ) in query.iter_mut()
+    {
+        let colors = state_style.resolve(
+            input,
+            hovered_entities.contains(&entity),
+            input_focus.get() == Some(entity),
+            invalid,
+        );
+
+        let mut new_style = *style;
+        if let Some(cursor) = colors.cursor {
+            new_style.cursor_color = cursor;
+        }
+        if let Some(selection) = colors.selection {
+            new_style.selection_color = selection;
+        }
+        if let Some(selected_text) = colors.selected_text {
+            new_style.selected_text_color = Some(selected_text);
+        }
+        style.set_if_neq(new_style);
+
+        if let Some(text) = colors.text {
+            text_color.set_if_neq(TextColor(text));
+        }
+
+        if let Some(background) = colors.background {
+            match background_color {
+                Some(mut background_color) => {
+                    background_color.set_if_neq(BackgroundColor(background));
+                }
+                None => {
+                    commands.entity(entity).insert(BackgroundColor(background));
+                }
+            }
+        }
+
+        if let Some(border) = colors.border {
+            match border_color {
+                Some(mut border_color) => {
+                    border_color.set_if_neq(BorderColor::all(border));
+                }
+                None => {
+                    commands.entity(entity).insert(BorderColor::all(border));
+                }
+            }
+        }
+
+        if let Some(color) = colors.outline
+            && let Some(mut outline) = outline
+            && outline.color != color
+        {
+            outline.color = color;
+        }
+    }
+}
@@ -1,14 +1,22 @@
+use crate::TextInputAutoSize;
 use crate::TextInputBuffer;
 use crate::TextInputGlyph;
+use crate::TextInputLargeDocument;
+use crate::TextInputLayoutError;
 use crate::TextInputLayoutInfo;
+use crate::TextInputMode;
 use crate::TextInputNode;
 use crate::TextInputPrompt;
 use crate::TextInputPromptLayoutInfo;
+use crate::TextInputStyle;
+use crate::edit::release_buffer_caches;
 use bevy::asset::AssetEvent;
 use bevy::asset::AssetId;
 use bevy::asset::Assets;
 use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::entity::Entity;
 use bevy::ecs::message::MessageReader;
+use bevy::ecs::message::MessageWriter;
 use bevy::ecs::resource::Resource;
 use bevy::ecs::system::Query;
 use bevy::ecs::system::Res;
@@ -16,6 +24,8 @@ use bevy::ecs::system::ResMut;
 use bevy::ecs::world::Ref;
 use bevy::image::Image;
 use bevy::image::TextureAtlasLayout;
+use bevy::input_focus::InputFocus;
+use bevy::log::error;
 use bevy::math::Rect;
 use bevy::math::UVec2;
 use bevy::math::Vec2;
@@ -29,10 +39,15 @@ use bevy::text::TextBounds;
 use bevy::text::TextError;
 use bevy::text::TextFont;
 use bevy::ui::ComputedNode;
+use bevy::ui::ComputedUiRenderTargetInfo;
+use bevy::ui::ContentSize;
+use bevy::ui::FixedMeasure;
+use bevy::ui::NodeMeasure;
 use cosmic_text;
 use cosmic_text::Buffer;
 use cosmic_text::Edit;
 use cosmic_text::Metrics;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 #[derive(Resource)]
@@ -58,6 +73,7 @@ impl Default for TextInputPipeline {
 
 #[derive(Clone)]
 struct FontFaceInfo {
+    face_id: cosmic_text::fontdb::ID,
     stretch: cosmic_text::fontdb::Stretch,
     style: cosmic_text::fontdb::Style,
     weight: cosmic_text::fontdb::Weight,
@@ -92,6 +108,7 @@ fn load_font_to_fontdb(
     let face = font_system.db().face(*face_id).unwrap();
 
     FontFaceInfo {
+        face_id: *face_id,
         stretch: face.stretch,
         style: face.style,
         weight: face.weight,
@@ -109,33 +126,359 @@ fn buffer_dimensions(buffer: &cosmic_text::Buffer) -> Vec2 {
     Vec2::new(width, height).ceil()
 }
 
+/// Sizes of the lines of a large document, with running totals so the size of the whole document is known without measuring every line.
+/// Edits only update the lines they touch, and lines are measured as they're laid out near the viewport.
+/// Lines that haven't been measured are counted as a single empty line.
+#[derive(Debug, Default)]
+pub(crate) struct DocumentExtents {
+    /// Size of each line of the buffer
+    lines: Vec<Vec2>,
+    /// Sum of the heights of the lines
+    height: f64,
+    /// Number of lines of each width, keyed by the width's bits, which sort in the same order as the non-negative widths
+    widths: BTreeMap<u32, usize>,
+    /// Height of lines that haven't been measured
+    line_height: f32,
+    /// False until the extents have been built from the buffer, edits are ignored until then
+    valid: bool,
+}
+
+impl DocumentExtents {
+    /// Size of the whole document
+    pub(crate) fn size(&self) -> Vec2 {
+        let width = self
+            .widths
+            .last_key_value()
+            .map_or(0., |(width, _)| f32::from_bits(*width));
+        Vec2::new(width, self.height as f32).ceil()
+    }
+
+    /// Discards the extents, they're rebuilt from every line of the buffer the next time they're needed
+    pub(crate) fn invalidate(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Rebuilds the extents from every line of the buffer if they're invalid or out of sync with the buffer
+    fn rebuild_if_needed(&mut self, buffer: &cosmic_text::Buffer) {
+        let line_height = buffer.metrics().line_height;
+        if self.valid && self.lines.len() == buffer.lines.len() && self.line_height == line_height {
+            return;
+        }
+
+        *self = Self {
+            line_height,
+            valid: true,
+            ..Default::default()
+        };
+        self.lines.reserve(buffer.lines.len());
+        for buffer_line in buffer.lines.iter() {
+            let extent = buffer_line
+                .layout_opt()
+                .map_or(self.unmeasured(), |layout| self.measure(layout));
+            self.add(extent);
+            self.lines.push(extent);
+        }
+    }
+
+    fn unmeasured(&self) -> Vec2 {
+        Vec2::new(0., self.line_height)
+    }
+
+    fn measure(&self, layout: &[cosmic_text::LayoutLine]) -> Vec2 {
+        layout.iter().fold(Vec2::ZERO, |extent, layout_line| {
+            Vec2::new(
+                layout_line.w.max(extent.x),
+                extent.y + layout_line.line_height_opt.unwrap_or(self.line_height),
+            )
+        })
+    }
+
+    fn add(&mut self, extent: Vec2) {
+        self.height += extent.y as f64;
+        if 0. < extent.x {
+            *self.widths.entry(extent.x.to_bits()).or_default() += 1;
+        }
+    }
+
+    fn subtract(&mut self, extent: Vec2) {
+        self.height -= extent.y as f64;
+        if 0. < extent.x
+            && let Some(count) = self.widths.get_mut(&extent.x.to_bits())
+        {
+            *count -= 1;
+            if *count == 0 {
+                self.widths.remove(&extent.x.to_bits());
+            }
+        }
+    }
+
+    fn set(&mut self, line_i: usize, extent: Vec2) {
+        let Some(&previous) = self.lines.get(line_i) else {
+            return;
+        };
+        if previous != extent {
+            self.subtract(previous);
+            self.add(extent);
+            self.lines[line_i] = extent;
+        }
+    }
+
+    fn insert_unmeasured(&mut self, line_i: usize, count: usize) {
+        let line_i = line_i.min(self.lines.len());
+        let extent = self.unmeasured();
+        self.height += count as f64 * extent.y as f64;
+        self.lines
+            .splice(line_i..line_i, std::iter::repeat_n(extent, count));
+    }
+
+    fn remove(&mut self, lines: std::ops::Range<usize>) {
+        let lines = lines.start.min(self.lines.len())..lines.end.min(self.lines.len());
+        let removed: Vec<Vec2> = self.lines.drain(lines).collect();
+        for extent in removed {
+            self.subtract(extent);
+        }
+    }
+
+    /// Updates the extents of the lines touched by an edit
+    pub(crate) fn apply_change(&mut self, change: &cosmic_text::Change) {
+        if !self.valid {
+            return;
+        }
+        for item in change.items.iter() {
+            let unmeasured = self.unmeasured();
+            self.set(item.start.line, unmeasured);
+            if item.insert {
+                self.insert_unmeasured(item.start.line + 1, item.end.line - item.start.line);
+            } else {
+                self.remove(item.start.line + 1..item.end.line + 1);
+            }
+        }
+    }
+
+    /// Updates the extents after the first `cleared` lines of the buffer are replaced by `added` lines appended to it,
+    /// then `removed` lines are removed from its start
+    pub(crate) fn append_lines(&mut self, cleared: usize, added: usize, removed: usize) {
+        if !self.valid {
+            return;
+        }
+        self.remove(0..cleared);
+        self.insert_unmeasured(self.lines.len(), added);
+        self.remove(0..removed);
+    }
+
+    /// Measures the laid out lines in `lines`, and returns the size of the whole document
+    fn update(&mut self, buffer: &cosmic_text::Buffer, lines: std::ops::Range<usize>) -> Vec2 {
+        self.rebuild_if_needed(buffer);
+        for line_i in lines {
+            if let Some(layout) = buffer.lines.get(line_i).and_then(|line| line.layout_opt()) {
+                let extent = self.measure(layout);
+                self.set(line_i, extent);
+            }
+        }
+        self.size()
+    }
+}
+
+/// Lays out a line that isn't visible and moves its glyphs into `next_glyph_cache`, generating any that aren't already in `glyph_cache`
+fn cache_line_glyphs(
+    buffer: &mut cosmic_text::Buffer,
+    line_i: usize,
+    next_line_id: &mut usize,
+    glyph_cache: &mut HashMap<(usize, usize), Vec<TextInputGlyph>>,
+    next_glyph_cache: &mut HashMap<(usize, usize), Vec<TextInputGlyph>>,
+    text_font: &TextFont,
+    text_input_pipeline: &mut TextInputPipeline,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    textures: &mut Assets<Image>,
+) -> Result<(), TextError> {
+    let line_id = match buffer.lines[line_i].metadata() {
+        Some(line_id) => line_id,
+        None => {
+            buffer.lines[line_i].set_metadata(*next_line_id);
+            *next_line_id += 1;
+            *next_line_id - 1
+        }
+    };
+
+    let Some(layout) = buffer.line_layout(&mut text_input_pipeline.font_system, line_i) else {
+        return Ok(());
+    };
+
+    for layout_line in layout {
+        let Some(first_glyph) = layout_line.glyphs.first() else {
+            continue;
+        };
+        let key = (line_id, first_glyph.start);
+        let run_glyphs = match glyph_cache.remove(&key) {
+            Some(run_glyphs) => run_glyphs,
+            None => {
+                let mut run_glyphs = Vec::with_capacity(layout_line.glyphs.len());
+                layout_run_glyphs(
+                    &layout_line.glyphs,
+                    0.,
+                    line_i,
+                    text_font,
+                    text_input_pipeline,
+                    texture_atlases,
+                    textures,
+                    &mut run_glyphs,
+                )?;
+                run_glyphs
+            }
+        };
+        next_glyph_cache.insert(key, run_glyphs);
+    }
+
+    Ok(())
+}
+
+/// Adds the glyphs of a layout run to the pipeline's font atlases and pushes their `TextInputGlyph`s to `glyphs`
+fn layout_run_glyphs(
+    layout_glyphs: &[cosmic_text::LayoutGlyph],
+    line_y: f32,
+    line_index: usize,
+    text_font: &TextFont,
+    text_input_pipeline: &mut TextInputPipeline,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    textures: &mut Assets<Image>,
+    glyphs: &mut Vec<TextInputGlyph>,
+) -> Result<(), TextError> {
+    let font_id = text_font.font.id();
+    let font_smoothing = text_font.font_smoothing;
+
+    let TextInputPipeline {
+        font_system,
+        swash_cache,
+        font_atlas_sets,
+        ..
+    } = text_input_pipeline;
+
+    let font_atlas_set = font_atlas_sets.entry(font_id).or_default();
+
+    for layout_glyph in layout_glyphs {
+        let mut temp_glyph;
+        let span_index = layout_glyph.metadata;
+
+        let layout_glyph = if font_smoothing == FontSmoothing::None {
+            // If font smoothing is disabled, round the glyph positions and sizes,
+            // effectively discarding all subpixel layout.
+            temp_glyph = layout_glyph.clone();
+            temp_glyph.x = temp_glyph.x.round();
+            temp_glyph.y = temp_glyph.y.round();
+            temp_glyph.w = temp_glyph.w.round();
+            temp_glyph.x_offset = temp_glyph.x_offset.round();
+            temp_glyph.y_offset = temp_glyph.y_offset.round();
+            temp_glyph.line_height_opt = temp_glyph.line_height_opt.map(f32::round);
+
+            &temp_glyph
+        } else {
+            layout_glyph
+        };
+
+        let physical_glyph = layout_glyph.physical((0., 0.), 1.);
+
+        let atlas_info = font_atlas_set
+            .get_glyph_atlas_info(physical_glyph.cache_key, font_smoothing)
+            .map(Ok)
+            .unwrap_or_else(|| {
+                font_atlas_set.add_glyph_to_atlas(
+                    texture_atlases,
+                    textures,
+                    font_system,
+                    swash_cache,
+                    layout_glyph,
+                    font_smoothing,
+                )
+            })?;
+
+        let texture_atlas = texture_atlases.get(atlas_info.texture_atlas).unwrap();
+        let location = atlas_info.location;
+        let glyph_rect = texture_atlas.textures[location.glyph_index];
+        let left = location.offset.x as f32;
+        let top = location.offset.y as f32;
+        let glyph_size = UVec2::new(glyph_rect.width(), glyph_rect.height());
+
+        // offset by half the size because the origin is center
+        let x = glyph_size.x as f32 / 2.0 + left + physical_glyph.x as f32;
+        let y = line_y.round() + physical_glyph.y as f32 - top + glyph_size.y as f32 / 2.0;
+
+        glyphs.push(TextInputGlyph {
+            position: Vec2::new(x, y),
+            size: glyph_size.as_vec2(),
+            atlas_info,
+            span_index,
+            byte_index: layout_glyph.start,
+            byte_length: layout_glyph.end - layout_glyph.start,
+            line_index,
+        });
+    }
+
+    Ok(())
+}
+
 pub fn text_input_system(
     mut textures: ResMut<Assets<Image>>,
     fonts: Res<Assets<Font>>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut text_query: Query<(
+        Entity,
         Ref<ComputedNode>,
         Ref<TextFont>,
         &mut TextInputLayoutInfo,
         &mut TextInputBuffer,
         Ref<TextInputNode>,
+        Option<Ref<TextInputAutoSize>>,
+        Option<&TextInputLargeDocument>,
     )>,
+    mut error_writer: MessageWriter<TextInputLayoutError>,
+    input_focus: Res<InputFocus>,
 ) {
-    for (node, text_font, text_input_layout_info, mut editor, input) in text_query.iter_mut() {
+    for (
+        entity,
+        node,
+        text_font,
+        text_input_layout_info,
+        mut editor,
+        input,
+        auto_size,
+        large_document,
+    ) in text_query.iter_mut()
+    {
         let layout_info = text_input_layout_info.into_inner();
-        if editor.needs_update || text_font.is_changed() || node.is_changed() || input.is_changed()
+        if editor.needs_update
+            || text_font.is_changed()
+            || node.is_changed()
+            || input.is_changed()
+            || auto_size.as_ref().is_some_and(Ref::is_changed)
         {
-            let bounds = TextBounds {
-                width: Some(node.size().x),
-                height: Some(node.size().y),
-            };
-
             let line_height = match text_font.line_height {
                 LineHeight::Px(h) => h,
                 LineHeight::RelativeToFont(r) => r * text_font.font_size,
             };
 
+            let scale_factor = node.inverse_scale_factor().recip();
+
+            // Auto-sized inputs are laid out unbounded along their auto-sized axis, up to its maximum.
+            let bounds = match (auto_size.as_deref(), input.mode) {
+                (Some(auto_size), TextInputMode::MultiLine { .. }) => TextBounds {
+                    width: Some(node.size().x),
+                    height: auto_size
+                        .max_lines
+                        .map(|max_lines| max_lines as f32 * line_height * scale_factor),
+                },
+                (Some(auto_size), TextInputMode::SingleLine) => TextBounds {
+                    width: auto_size
+                        .max_width
+                        .map(|max_width| max_width * scale_factor),
+                    height: Some(node.size().y),
+                },
+                (None, _) => TextBounds {
+                    width: Some(node.size().x),
+                    height: Some(node.size().y),
+                },
+            };
+
             let result = editor.editor.with_buffer_mut(|buffer| {
                 let TextInputPipeline {
                     font_system,
@@ -149,16 +492,23 @@ pub fn text_input_system(
                 let face_info =
                     load_font_to_fontdb(&text_font, font_system, map_handle_to_font_id, &fonts);
 
-                let mut metrics = Metrics::new(text_font.font_size, line_height)
-                    .scale(node.inverse_scale_factor().recip());
+                let mut metrics =
+                    Metrics::new(text_font.font_size, line_height).scale(scale_factor);
 
                 metrics.font_size = metrics.font_size.max(0.000001);
                 metrics.line_height = metrics.line_height.max(0.000001);
 
+                // The cached glyphs are positioned relative to their runs, so they only need to be regenerated when
+                // the wrap width, metrics, wrapping, attributes or alignment change, not the height or the node's position.
+                let previous_layout = (buffer.size().0, buffer.metrics(), buffer.wrap());
+
                 buffer.set_metrics_and_size(font_system, metrics, bounds.width, bounds.height);
 
                 buffer.set_wrap(font_system, input.mode.wrap());
 
+                let mut layout_changed =
+                    previous_layout != (buffer.size().0, buffer.metrics(), buffer.wrap());
+
                 let attrs = cosmic_text::Attrs::new()
                     .metadata(0)
                     .family(cosmic_text::Family::Name(&face_info.family_name))
@@ -167,18 +517,31 @@ pub fn text_input_system(
                     .weight(face_info.weight)
                     .metrics(metrics);
 
-                let text = crate::get_text(buffer);
-                buffer.set_text(font_system, &text, &attrs, cosmic_text::Shaping::Advanced);
+                // Only lines with changed attributes are reshaped, instead of resetting the text of the whole buffer.
+                if buffer.lines.is_empty() {
+                    buffer.set_text(font_system, "", &attrs, cosmic_text::Shaping::Advanced);
+                } else {
+                    let attrs_list = cosmic_text::AttrsList::new(&attrs);
+                    for buffer_line in buffer.lines.iter_mut() {
+                        layout_changed |= buffer_line.set_attrs_list(attrs_list.clone());
+                    }
+                }
+
                 let align = Some(input.justification.into());
                 for buffer_line in buffer.lines.iter_mut() {
-                    buffer_line.set_align(align);
+                    layout_changed |= buffer_line.set_align(align);
                 }
 
-                Ok(())
+                Ok(layout_changed)
             });
 
-            if result.is_ok() {
+            if let Ok(layout_changed) = result {
                 editor.needs_update = false;
+                if layout_changed {
+                    // The layouts of the lines may have changed without their caches being reset
+                    editor.glyph_cache.clear();
+                    editor.document_extents.invalidate();
+                }
                 editor.editor.set_redraw(true);
             } else {
                 editor.needs_update = true;
@@ -186,6 +549,12 @@ pub fn text_input_system(
             }
         }
 
+        // Unfocused inputs are only shaped and drawn when something has changed, otherwise their cached glyphs are reused
+        let is_focused = input_focus.get() == Some(entity);
+        if !is_focused && !editor.editor.redraw() {
+            continue;
+        }
+
         editor
             .editor
             .shape_as_needed(&mut text_input_pipeline.font_system, false);
@@ -194,6 +563,10 @@ pub fn text_input_system(
         let TextInputBuffer {
             editor,
             selection_rects,
+            glyph_cache,
+            document_extents,
+            next_line_id,
+            layout_error_reported,
             ..
         } = &mut *editor;
 
@@ -202,7 +575,38 @@ pub fn text_input_system(
             selection_rects.clear();
 
             let result = editor.with_buffer_mut(|buffer| {
-                let box_size = buffer_dimensions(buffer);
+                // Lines that have been reset since they were last drawn have no metadata.
+                // Give them a new id so that any cached glyphs for them aren't reused.
+                let visible_lines: Vec<usize> =
+                    buffer.layout_runs().map(|run| run.line_i).collect();
+                let visible_line_range = visible_lines
+                    .first()
+                    .zip(visible_lines.last())
+                    .map(|(&first, &last)| first..last + 1);
+                for line_i in visible_lines {
+                    let buffer_line = &mut buffer.lines[line_i];
+                    if buffer_line.metadata().is_none() {
+                        buffer_line.set_metadata(*next_line_id);
+                        *next_line_id += 1;
+                    }
+                }
+
+                // Glyphs outside of the horizontal viewport are culled
+                let scroll_x = buffer.scroll().horizontal;
+                let viewport_width = buffer.size().0.unwrap_or(f32::INFINITY);
+
+                // Lines near the viewport of a large document are laid out ahead of time
+                let overscan_range = large_document.zip(visible_line_range.clone()).map(
+                    |(large_document, visible_line_range)| {
+                        visible_line_range
+                            .start
+                            .saturating_sub(large_document.overscan_lines)
+                            ..(visible_line_range.end + large_document.overscan_lines)
+                                .min(buffer.lines.len())
+                    },
+                );
+
+                let mut next_glyph_cache = HashMap::default();
                 let result = buffer.layout_runs().try_for_each(|run| {
                     if let Some(selection) = selection
                         && let Some((x0, w)) = run.highlight(selection.0, selection.1)
@@ -214,104 +618,106 @@ pub fn text_input_system(
                         selection_rects.push(r);
                     }
 
-                    run.glyphs
-                        .iter()
-                        .map(move |layout_glyph| (layout_glyph, run.line_y, run.line_i))
-                        .try_for_each(|(layout_glyph, line_y, line_i)| {
-                            let mut temp_glyph;
-                            let span_index = layout_glyph.metadata;
-                            let font_id = text_font.font.id();
-                            let font_smoothing = text_font.font_smoothing;
-
-                            let layout_glyph = if font_smoothing == FontSmoothing::None {
-                                // If font smoothing is disabled, round the glyph positions and sizes,
-                                // effectively discarding all subpixel layout.
-                                temp_glyph = layout_glyph.clone();
-                                temp_glyph.x = temp_glyph.x.round();
-                                temp_glyph.y = temp_glyph.y.round();
-                                temp_glyph.w = temp_glyph.w.round();
-                                temp_glyph.x_offset = temp_glyph.x_offset.round();
-                                temp_glyph.y_offset = temp_glyph.y_offset.round();
-                                temp_glyph.line_height_opt =
-                                    temp_glyph.line_height_opt.map(f32::round);
-
-                                &temp_glyph
-                            } else {
-                                layout_glyph
-                            };
-
-                            let TextInputPipeline {
-                                font_system,
-                                swash_cache,
-                                font_atlas_sets,
-                                ..
-                            } = &mut *text_input_pipeline;
-
-                            let font_atlas_set = font_atlas_sets.entry(font_id).or_default();
-
-                            let physical_glyph = layout_glyph.physical((0., 0.), 1.);
-
-                            let atlas_info = font_atlas_set
-                                .get_glyph_atlas_info(physical_glyph.cache_key, font_smoothing)
-                                .map(Ok)
-                                .unwrap_or_else(|| {
-                                    font_atlas_set.add_glyph_to_atlas(
-                                        &mut texture_atlases,
-                                        &mut textures,
-                                        font_system,
-                                        swash_cache,
-                                        layout_glyph,
-                                        font_smoothing,
-                                    )
-                                })?;
-
-                            let texture_atlas =
-                                texture_atlases.get(atlas_info.texture_atlas).unwrap();
-                            let location = atlas_info.location;
-                            let glyph_rect = texture_atlas.textures[location.glyph_index];
-                            let left = location.offset.x as f32;
-                            let top = location.offset.y as f32;
-                            let glyph_size = UVec2::new(glyph_rect.width(), glyph_rect.height());
-
-                            // offset by half the size because the origin is center
-                            let x = glyph_size.x as f32 / 2.0 + left + physical_glyph.x as f32;
-                            let y = line_y.round() + physical_glyph.y as f32 - top
-                                + glyph_size.y as f32 / 2.0;
-
-                            let position = Vec2::new(x, y);
-
-                            let pos_glyph = TextInputGlyph {
-                                position,
-                                size: glyph_size.as_vec2(),
-                                atlas_info,
-                                span_index,
-                                byte_index: layout_glyph.start,
-                                byte_length: layout_glyph.end - layout_glyph.start,
-                                line_index: line_i,
-                            };
-                            layout_info.glyphs.push(pos_glyph);
-                            Ok(())
+                    let Some(first_glyph) = run.glyphs.first() else {
+                        return Ok(());
+                    };
+
+                    let line_id = buffer.lines[run.line_i].metadata().unwrap_or(usize::MAX);
+                    let key = (line_id, first_glyph.start);
+
+                    // Only the glyphs of runs that have changed or are newly visible are regenerated
+                    let run_glyphs = match glyph_cache.remove(&key) {
+                        Some(run_glyphs) => run_glyphs,
+                        None => {
+                            let mut run_glyphs = Vec::with_capacity(run.glyphs.len());
+                            layout_run_glyphs(
+                                run.glyphs,
+                                0.,
+                                run.line_i,
+                                &text_font,
+                                &mut text_input_pipeline,
+                                &mut texture_atlases,
+                                &mut textures,
+                                &mut run_glyphs,
+                            )?;
+                            run_glyphs
+                        }
+                    };
+
+                    let line_y = run.line_y.round();
+                    layout_info.glyphs.extend(
+                        run_glyphs
+                            .iter()
+                            .filter(|glyph| {
+                                scroll_x <= glyph.position.x + 0.5 * glyph.size.x
+                                    && glyph.position.x - 0.5 * glyph.size.x
+                                        <= scroll_x + viewport_width
+                            })
+                            .map(|glyph| TextInputGlyph {
+                                position: glyph.position + line_y * Vec2::Y,
+                                line_index: run.line_i,
+                                ..glyph.clone()
+                            }),
+                    );
+
+                    next_glyph_cache.insert(key, run_glyphs);
+                    Ok(())
+                });
+
+                let result = result.and_then(|()| {
+                    let (Some(overscan_range), Some(visible_line_range)) =
+                        (overscan_range.clone(), visible_line_range)
+                    else {
+                        return Ok(());
+                    };
+                    overscan_range
+                        .filter(|line_i| !visible_line_range.contains(line_i))
+                        .try_for_each(|line_i| {
+                            cache_line_glyphs(
+                                buffer,
+                                line_i,
+                                next_line_id,
+                                glyph_cache,
+                                &mut next_glyph_cache,
+                                &text_font,
+                                &mut text_input_pipeline,
+                                &mut texture_atlases,
+                                &mut textures,
+                            )
                         })
                 });
 
+                *glyph_cache = next_glyph_cache;
+
                 // Check result.
                 result?;
 
-                layout_info.size = box_size;
+                layout_info.size = if large_document.is_some() {
+                    document_extents.update(buffer, overscan_range.unwrap_or_default())
+                } else {
+                    buffer_dimensions(buffer)
+                };
                 Ok(())
             });
 
             match result {
-                Err(TextError::NoSuchFont) => {
+                Err(error) => {
                     // There was an error processing the text layout, try again next frame
-                }
-                Err(e @ (TextError::FailedToAddGlyph(_) | TextError::FailedToGetGlyphImage(_))) => {
-                    panic!("Fatal error when processing text: {e}.");
+                    if error != TextError::NoSuchFont && !*layout_error_reported {
+                        error!("Failed to lay out text input {entity}: {error}.");
+                        error_writer.write(TextInputLayoutError { entity, error });
+                        *layout_error_reported = true;
+                    }
                 }
                 Ok(()) => {
+                    *layout_error_reported = false;
                     layout_info.size.x *= node.inverse_scale_factor();
                     layout_info.size.y *= node.inverse_scale_factor();
                     editor.set_redraw(false);
+                    if !is_focused && input.release_on_blur {
+                        glyph_cache.clear();
+                        editor.with_buffer_mut(release_buffer_caches);
+                    }
                 }
             }
         }
@@ -324,6 +730,7 @@ pub fn text_input_prompt_system(
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut text_query: Query<(
+        Entity,
         Ref<ComputedNode>,
         Ref<TextFont>,
         &mut TextInputPromptLayoutInfo,
@@ -331,8 +738,9 @@ pub fn text_input_prompt_system(
         Ref<TextInputNode>,
         Ref<TextInputPrompt>,
     )>,
+    mut error_writer: MessageWriter<TextInputLayoutError>,
 ) {
-    for (node, text_font, text_input_layout_info, mut editor, input, prompt) in
+    for (entity, node, text_font, text_input_layout_info, mut editor, input, prompt) in
         text_query.iter_mut()
     {
         let layout_info = text_input_layout_info.into_inner();
@@ -355,13 +763,14 @@ pub fn text_input_prompt_system(
                 handle_to_font_id_map: map_handle_to_font_id,
                 ..
             } = &mut *text_input_pipeline;
-            if !fonts.contains(text_font.font.id()) {
+
+            let font = prompt.font.as_ref().unwrap_or(text_font.as_ref());
+
+            if !fonts.contains(font.font.id()) {
                 editor.prompt_buffer = None;
                 continue;
             }
 
-            let font = prompt.font.as_ref().unwrap_or(text_font.as_ref());
-
             let line_height = match text_font.line_height {
                 LineHeight::Px(h) => h,
                 LineHeight::RelativeToFont(r) => r * font.font_size,
@@ -423,95 +832,33 @@ pub fn text_input_prompt_system(
 
             let box_size = buffer_dimensions(buffer);
             let result = buffer.layout_runs().try_for_each(|run| {
-                run.glyphs
-                    .iter()
-                    .map(move |layout_glyph| (layout_glyph, run.line_y, run.line_i))
-                    .try_for_each(|(layout_glyph, line_y, line_i)| {
-                        let mut temp_glyph;
-                        let span_index = layout_glyph.metadata;
-                        let font_id = text_font.font.id();
-                        let font_smoothing = text_font.font_smoothing;
-
-                        let layout_glyph = if font_smoothing == FontSmoothing::None {
-                            // If font smoothing is disabled, round the glyph positions and sizes,
-                            // effectively discarding all subpixel layout.
-                            temp_glyph = layout_glyph.clone();
-                            temp_glyph.x = temp_glyph.x.round();
-                            temp_glyph.y = temp_glyph.y.round();
-                            temp_glyph.w = temp_glyph.w.round();
-                            temp_glyph.x_offset = temp_glyph.x_offset.round();
-                            temp_glyph.y_offset = temp_glyph.y_offset.round();
-                            temp_glyph.line_height_opt = temp_glyph.line_height_opt.map(f32::round);
-
-                            &temp_glyph
-                        } else {
-                            layout_glyph
-                        };
-
-                        let TextInputPipeline {
-                            font_system,
-                            swash_cache,
-                            font_atlas_sets,
-                            ..
-                        } = &mut *text_input_pipeline;
-
-                        let font_atlas_set = font_atlas_sets.entry(font_id).or_default();
-
-                        let physical_glyph = layout_glyph.physical((0., 0.), 1.);
-
-                        let atlas_info = font_atlas_set
-                            .get_glyph_atlas_info(physical_glyph.cache_key, font_smoothing)
-                            .map(Ok)
-                            .unwrap_or_else(|| {
-                                font_atlas_set.add_glyph_to_atlas(
-                                    &mut texture_atlases,
-                                    &mut textures,
-                                    font_system,
-                                    swash_cache,
-                                    layout_glyph,
-                                    font_smoothing,
-                                )
-                            })?;
-
-                        let texture_atlas = texture_atlases.get(atlas_info.texture_atlas).unwrap();
-                        let location = atlas_info.location;
-                        let glyph_rect = texture_atlas.textures[location.glyph_index];
-                        let left = location.offset.x as f32;
-                        let top = location.offset.y as f32;
-                        let glyph_size = UVec2::new(glyph_rect.width(), glyph_rect.height());
-
-                        // offset by half the size because the origin is center
-                        let x = glyph_size.x as f32 / 2.0 + left + physical_glyph.x as f32;
-                        let y = line_y.round() + physical_glyph.y as f32 - top
-                            + glyph_size.y as f32 / 2.0;
-
-                        let position = Vec2::new(x, y);
-
-                        let pos_glyph = TextInputGlyph {
-                            position,
-                            size: glyph_size.as_vec2(),
-                            atlas_info,
-                            span_index,
-                            byte_index: layout_glyph.start,
-                            byte_length: layout_glyph.end - layout_glyph.start,
-                            line_index: line_i,
-                        };
-                        layout_info.glyphs.push(pos_glyph);
-                        Ok(())
-                    })
+                layout_run_glyphs(
+                    run.glyphs,
+                    run.line_y,
+                    run.line_i,
+                    font,
+                    &mut text_input_pipeline,
+                    &mut texture_atlases,
+                    &mut textures,
+                    &mut layout_info.glyphs,
+                )
             });
 
             layout_info.size = box_size;
 
             match result {
-                Err(TextError::NoSuchFont) => {
-                    editor.prompt_buffer = None;
+                Err(error) => {
                     // There was an error processing the text layout, try again next frame
-                }
-                Err(e @ (TextError::FailedToAddGlyph(_) | TextError::FailedToGetGlyphImage(_))) => {
-                    panic!("Fatal error when processing text: {e}.");
+                    editor.prompt_buffer = None;
+                    layout_info.glyphs.clear();
+                    if error != TextError::NoSuchFont && !editor.prompt_layout_error_reported {
+                        error!("Failed to lay out text input prompt for {entity}: {error}.");
+                        error_writer.write(TextInputLayoutError { entity, error });
+                        editor.prompt_layout_error_reported = true;
+                    }
                 }
                 Ok(()) => {
+                    editor.prompt_layout_error_reported = false;
                     layout_info.size.x *= node.inverse_scale_factor();
                     layout_info.size.y *= node.inverse_scale_factor();
                 }
@@ -520,6 +867,99 @@ pub fn text_input_prompt_system(
     }
 }
 
+/// Width of the `0` glyph (the CSS `ch` unit) in a font of size `font_size`
+fn char_width(
+    font_system: &mut cosmic_text::FontSystem,
+    face_id: cosmic_text::fontdb::ID,
+    font_size: f32,
+) -> f32 {
+    font_system
+        .get_font(face_id)
+        .and_then(|font| {
+            let face = font.rustybuzz();
+            let glyph_id = face.glyph_index('0')?;
+            let advance = face.glyph_hor_advance(glyph_id)?;
+            Some(advance as f32 * font_size / face.units_per_em() as f32)
+        })
+        // If the font has no `0` glyph fall back to half an em, as in CSS
+        .unwrap_or(0.5 * font_size)
+}
+
+/// Sets the `ContentSize` of text inputs.
+///
+/// The intrinsic size of a text input is one line tall and `size_in_chars` characters wide.
+/// Inputs with a `TextInputAutoSize` are sized to fit their text layouts along their auto-sized axis.
+pub fn text_input_measure_system(
+    fonts: Res<Assets<Font>>,
+    mut text_input_pipeline: ResMut<TextInputPipeline>,
+    mut query: Query<(
+        &TextInputNode,
+        &TextFont,
+        Option<&TextInputAutoSize>,
+        &TextInputLayoutInfo,
+        &TextInputStyle,
+        &ComputedUiRenderTargetInfo,
+        &mut TextInputBuffer,
+        &mut ContentSize,
+    )>,
+) {
+    let TextInputPipeline {
+        font_system,
+        handle_to_font_id_map: map_handle_to_font_id,
+        ..
+    } = &mut *text_input_pipeline;
+
+    for (input, text_font, auto_size, layout_info, style, target, mut buffer, mut content_size) in
+        query.iter_mut()
+    {
+        if !fonts.contains(text_font.font.id()) {
+            continue;
+        }
+
+        let face_info = load_font_to_fontdb(text_font, font_system, map_handle_to_font_id, &fonts);
+
+        let scale_factor = target.scale_factor();
+        let line_height = match text_font.line_height {
+            LineHeight::Px(h) => h,
+            LineHeight::RelativeToFont(r) => r * text_font.font_size,
+        } * scale_factor;
+        // Leave space for the cursor at the end of the line
+        let cursor_width = style.cursor_width * scale_factor;
+        let intrinsic_width = input.size_in_chars as f32
+            * char_width(font_system, face_info.face_id, text_font.font_size)
+            * scale_factor
+            + cursor_width;
+        let size = layout_info.size * scale_factor;
+
+        let measured_size = match (auto_size, input.mode) {
+            (Some(auto_size), TextInputMode::MultiLine { .. }) => {
+                let min_height = auto_size.min_lines as f32 * line_height;
+                let max_height = auto_size
+                    .max_lines
+                    .map_or(f32::INFINITY, |max_lines| max_lines as f32 * line_height);
+                Vec2::new(intrinsic_width, size.y.min(max_height).max(min_height))
+            }
+            (Some(auto_size), TextInputMode::SingleLine) => {
+                let min_width = auto_size.min_width * scale_factor;
+                let max_width = auto_size
+                    .max_width
+                    .map_or(f32::INFINITY, |max_width| max_width * scale_factor);
+                let width = size.x + cursor_width;
+                Vec2::new(width.min(max_width).max(min_width), line_height)
+            }
+            (None, _) => Vec2::new(intrinsic_width, line_height),
+        }
+        .ceil();
+
+        if buffer.measured_size != Some(measured_size) {
+            buffer.measured_size = Some(measured_size);
+            content_size.set(NodeMeasure::Fixed(FixedMeasure {
+                size: measured_size,
+            }));
+        }
+    }
+}
+
 pub fn remove_dropped_font_atlas_sets_from_text_input_pipeline(
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut font_events: MessageReader<AssetEvent<Font>>,
@@ -530,3 +970,71 @@ pub fn remove_dropped_font_atlas_sets_from_text_input_pipeline(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::TextInputEdit;
+    use crate::actions::apply_edit;
+    use cosmic_text::Attrs;
+    use cosmic_text::Editor;
+    use cosmic_text::Family;
+    use cosmic_text::FontSystem;
+    use cosmic_text::Shaping;
+    use cosmic_text::fontdb;
+
+    fn font_system() -> FontSystem {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(include_bytes!("../assets/fonts/FiraMono-Medium.ttf").to_vec());
+        FontSystem::new_with_locale_and_db("en-US".to_string(), db)
+    }
+
+    fn lay_out_all_lines(font_system: &mut FontSystem, buffer: &mut Buffer) {
+        for line_i in 0..buffer.lines.len() {
+            buffer.line_layout(font_system, line_i);
+        }
+    }
+
+    #[test]
+    fn running_totals_match_a_full_measure_after_edits() {
+        let mut font_system = font_system();
+        let mut buffer = Buffer::new(&mut font_system, Metrics::new(10., 10.));
+        buffer.set_text(
+            &mut font_system,
+            "one\ntwo\nthree\nfour",
+            &Attrs::new().family(Family::Name("Fira Mono")),
+            Shaping::Advanced,
+        );
+        lay_out_all_lines(&mut font_system, &mut buffer);
+        let mut extents = DocumentExtents::default();
+        extents.update(&buffer, 0..0);
+
+        let mut editor = Editor::new(buffer);
+        let mut changes = cosmic_undo_2::Commands::default();
+        let input = TextInputNode::default();
+        for edit in [
+            TextInputEdit::Paste("a much longer line\nand\nmore".to_string()),
+            TextInputEdit::Backspace,
+            TextInputEdit::SelectAll,
+            TextInputEdit::Paste("x\ny".to_string()),
+            TextInputEdit::Enter,
+        ] {
+            apply_edit(
+                edit,
+                &mut editor.borrow_with(&mut font_system),
+                &mut changes,
+                &input,
+                None,
+                Some(&mut extents),
+            );
+            editor.with_buffer_mut(|buffer| {
+                assert_eq!(extents.lines.len(), buffer.lines.len());
+                lay_out_all_lines(&mut font_system, buffer);
+                let size = extents.update(buffer, 0..buffer.lines.len());
+
+                let mut measured = DocumentExtents::default();
+                assert_eq!(size, measured.update(buffer, 0..0));
+            });
+        }
+    }
+}
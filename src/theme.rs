@@ -0,0 +1,303 @@
+use crate::TextInputPrompt;
+use crate::TextInputStateStyle;
+use crate::TextInputStyle;
+use bevy::asset::Asset;
+use bevy::asset::AssetEvent;
+use bevy::asset::AssetId;
+use bevy::asset::AssetLoader;
+use bevy::asset::Assets;
+use bevy::asset::Handle;
+use bevy::asset::LoadContext;
+use bevy::asset::io::Reader;
+use bevy::color::Color;
+use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::message::MessageReader;
+use bevy::ecs::reflect::AppTypeRegistry;
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::ecs::system::Commands;
+use bevy::ecs::system::Query;
+use bevy::ecs::system::Res;
+use bevy::ecs::world::FromWorld;
+use bevy::ecs::world::Ref;
+use bevy::ecs::world::World;
+use bevy::platform::collections::HashSet;
+use bevy::reflect::FromReflect;
+use bevy::reflect::Reflect;
+use bevy::reflect::TypePath;
+use bevy::reflect::TypeRegistry;
+use bevy::reflect::TypeRegistryArc;
+use bevy::reflect::serde::TypedReflectDeserializer;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::text::Font;
+use bevy::text::TextColor;
+use bevy::text::TextFont;
+use serde::de::DeserializeSeed;
+
+/// Shared styling for text inputs, loaded from a `.text_input_theme.ron` file.
+/// Applied to every text input with a [`TextInputThemeHandle`] for the theme when the theme is loaded or modified,
+/// so with Bevy's `file_watcher` feature enabled, edits to the file update the inputs live.
+/// `None` fields are left unchanged.
+#[derive(Asset, TypePath, Clone, Debug, Default)]
+pub struct TextInputTheme {
+    /// Sets the font of the input's `TextFont`
+    #[dependency]
+    pub font: Option<Handle<Font>>,
+    /// Sets the font size of the input's `TextFont`
+    pub font_size: Option<f32>,
+    /// Sets the input's `TextColor`
+    pub text_color: Option<Color>,
+    /// Replaces the input's `TextInputStyle`
+    pub style: Option<TextInputStyle>,
+    /// Replaces the input's `TextInputStateStyle`
+    pub state_style: Option<TextInputStateStyle>,
+    /// Sets the font of the input's `TextInputPrompt`
+    #[dependency]
+    pub prompt_font: Option<Handle<Font>>,
+    /// Sets the font size of the input's `TextInputPrompt`
+    pub prompt_font_size: Option<f32>,
+    /// Sets the text color of the input's `TextInputPrompt`
+    pub prompt_color: Option<Color>,
+}
+
+/// The contents of a `.text_input_theme.ron` file, with asset paths in place of the theme's font handles.
+/// Any field can be left out.
+///
+/// ```ron
+/// (
+///     font: Some("fonts/FiraMono-Medium.ttf"),
+///     font_size: Some(20.0),
+///     text_color: Some(Srgba((red: 1.0, green: 1.0, blue: 0.8, alpha: 1.0))),
+///     style: Some((
+///         cursor_width: 2.0,
+///     )),
+/// )
+/// ```
+#[derive(Reflect, Clone, Debug, Default)]
+#[reflect(Default)]
+pub struct TextInputThemeFile {
+    pub font: Option<String>,
+    pub font_size: Option<f32>,
+    pub text_color: Option<Color>,
+    pub style: Option<TextInputStyle>,
+    pub state_style: Option<TextInputStateStyle>,
+    pub prompt_font: Option<String>,
+    pub prompt_font_size: Option<f32>,
+    pub prompt_color: Option<Color>,
+}
+
+/// The theme used by a text input
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputThemeHandle(pub Handle<TextInputTheme>);
+
+#[derive(Debug)]
+pub enum TextInputThemeLoaderError {
+    /// The theme file couldn't be read
+    Io(std::io::Error),
+    /// The theme file isn't valid RON
+    Ron(ron::error::SpannedError),
+    /// The theme file's contents don't match `TextInputThemeFile`
+    InvalidTheme,
+}
+
+impl std::fmt::Display for TextInputThemeLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read text input theme: {error}"),
+            Self::Ron(error) => write!(f, "could not parse text input theme: {error}"),
+            Self::InvalidTheme => write!(f, "invalid text input theme"),
+        }
+    }
+}
+
+impl std::error::Error for TextInputThemeLoaderError {}
+
+impl From<std::io::Error> for TextInputThemeLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for TextInputThemeLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Ron(error)
+    }
+}
+
+/// Loads `TextInputTheme`s from `.text_input_theme.ron` files
+pub struct TextInputThemeLoader {
+    type_registry: TypeRegistryArc,
+}
+
+impl FromWorld for TextInputThemeLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            type_registry: world.resource::<AppTypeRegistry>().0.clone(),
+        }
+    }
+}
+
+/// Deserializes the contents of a `.text_input_theme.ron` file
+fn parse_theme_file(
+    bytes: &[u8],
+    type_registry: &TypeRegistry,
+) -> Result<TextInputThemeFile, TextInputThemeLoaderError> {
+    let mut deserializer = ron::de::Deserializer::from_bytes(bytes)?;
+    let value = TypedReflectDeserializer::of::<TextInputThemeFile>(type_registry)
+        .deserialize(&mut deserializer)
+        .map_err(|error| deserializer.span_error(error))?;
+    TextInputThemeFile::from_reflect(value.as_partial_reflect())
+        .ok_or(TextInputThemeLoaderError::InvalidTheme)
+}
+
+impl AssetLoader for TextInputThemeLoader {
+    type Asset = TextInputTheme;
+    type Settings = ();
+    type Error = TextInputThemeLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<TextInputTheme, TextInputThemeLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let file = parse_theme_file(&bytes, &self.type_registry.read())?;
+
+        Ok(TextInputTheme {
+            font: file.font.map(|path| load_context.load(path)),
+            font_size: file.font_size,
+            text_color: file.text_color,
+            style: file.style,
+            state_style: file.state_style,
+            prompt_font: file.prompt_font.map(|path| load_context.load(path)),
+            prompt_font_size: file.prompt_font_size,
+            prompt_color: file.prompt_color,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["text_input_theme.ron"]
+    }
+}
+
+/// Applies themes to text inputs when their `TextInputThemeHandle` is set, or when the theme is loaded or modified
+pub fn apply_text_input_themes(
+    mut commands: Commands,
+    mut theme_events: MessageReader<AssetEvent<TextInputTheme>>,
+    themes: Res<Assets<TextInputTheme>>,
+    mut query: Query<(
+        Entity,
+        Ref<TextInputThemeHandle>,
+        &mut TextInputStyle,
+        &mut TextFont,
+        &mut TextColor,
+        Option<&mut TextInputPrompt>,
+    )>,
+) {
+    let changed_themes: HashSet<AssetId<TextInputTheme>> = theme_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, theme_handle, mut style, mut text_font, mut text_color, prompt) in query.iter_mut()
+    {
+        if !theme_handle.is_changed() && !changed_themes.contains(&theme_handle.0.id()) {
+            continue;
+        }
+
+        let Some(theme) = themes.get(&theme_handle.0) else {
+            continue;
+        };
+
+        if let Some(font) = &theme.font {
+            text_font.font = font.clone();
+        }
+        if let Some(font_size) = theme.font_size {
+            text_font.font_size = font_size;
+        }
+        if let Some(color) = theme.text_color {
+            text_color.0 = color;
+        }
+        if let Some(theme_style) = theme.style {
+            *style = theme_style;
+        }
+        if let Some(state_style) = &theme.state_style {
+            commands.entity(entity).insert(state_style.clone());
+        }
+
+        if let Some(mut prompt) = prompt {
+            if theme.prompt_font.is_some() || theme.prompt_font_size.is_some() {
+                let prompt_font = prompt.font.get_or_insert_with(|| text_font.clone());
+                if let Some(font) = &theme.prompt_font {
+                    prompt_font.font = font.clone();
+                }
+                if let Some(font_size) = theme.prompt_font_size {
+                    prompt_font.font_size = font_size;
+                }
+            }
+            if let Some(color) = theme.prompt_color {
+                prompt.color = Some(color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::color::Srgba;
+
+    fn type_registry() -> TypeRegistry {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<TextInputThemeFile>();
+        type_registry
+    }
+
+    #[test]
+    fn example_theme_deserializes() {
+        let file = parse_theme_file(
+            include_bytes!("../assets/themes/example.text_input_theme.ron"),
+            &type_registry(),
+        )
+        .unwrap();
+
+        assert_eq!(file.font.as_deref(), Some("fonts/FiraMono-Medium.ttf"));
+        assert_eq!(file.font_size, Some(22.));
+        assert_eq!(
+            file.text_color,
+            Some(Color::Srgba(Srgba::new(1., 0.95, 0.8, 1.)))
+        );
+        let style = file.style.unwrap();
+        assert_eq!(style.cursor_width, 2.);
+        assert_eq!(style.cursor_color, Color::Srgba(Srgba::WHITE));
+        let state_style = file.state_style.unwrap();
+        assert_eq!(
+            state_style.hovered.background,
+            Some(Color::Srgba(Srgba::new(0.1, 0.1, 0.3, 1.)))
+        );
+        assert_eq!(file.prompt_font, None);
+    }
+
+    #[test]
+    fn omitted_fields_are_none() {
+        let file = parse_theme_file(b"(font_size: Some(12.0))", &type_registry()).unwrap();
+
+        assert_eq!(file.font_size, Some(12.));
+        assert_eq!(file.font, None);
+        assert!(file.style.is_none());
+        assert!(file.state_style.is_none());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(parse_theme_file(b"(font_colour: None)", &type_registry()).is_err());
+    }
+}
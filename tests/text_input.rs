@@ -0,0 +1,312 @@
+//! Headless tests of the systems that apply queued actions to text inputs
+
+use bevy::{input_focus::InputFocus, picking::hover::HoverMap, prelude::*, ui::ComputedNode};
+use bevy_ui_text_input::{
+    SubmitText, TextInputBuffer, TextInputContents, TextInputLargeDocument, TextInputLayoutError,
+    TextInputLayoutInfo, TextInputNode, TextInputQueue, TextInputReleasedText,
+    actions::{TextInputAction, TextInputEdit},
+    clipboard::Clipboard,
+    edit::{process_text_input_queues, release_idle_text_inputs, restore_released_text_inputs},
+    text_input_pipeline::{TextInputPipeline, text_input_system},
+    update_text_input_contents,
+};
+use cosmic_text::Edit;
+
+fn app() -> App {
+    let mut app = App::new();
+    app.init_resource::<TextInputPipeline>()
+        .init_resource::<Clipboard>()
+        .init_resource::<InputFocus>()
+        .add_message::<SubmitText>()
+        .add_systems(
+            Update,
+            (process_text_input_queues, update_text_input_contents).chain(),
+        );
+    app
+}
+
+fn queue(app: &mut App, entity: Entity, action: TextInputAction) {
+    app.world_mut()
+        .get_mut::<TextInputQueue>(entity)
+        .unwrap()
+        .add(action);
+}
+
+fn contents(app: &App, entity: Entity) -> &str {
+    app.world().get::<TextInputContents>(entity).unwrap().get()
+}
+
+#[test]
+fn contents_follow_queued_edits() {
+    let mut app = app();
+    let entity = app
+        .world_mut()
+        .spawn((TextInputNode::default(), TextInputContents::default()))
+        .id();
+    app.update();
+
+    queue(
+        &mut app,
+        entity,
+        TextInputAction::Edit(TextInputEdit::Paste("hello".to_string())),
+    );
+    app.update();
+    assert_eq!(contents(&app, entity), "hello");
+
+    queue(
+        &mut app,
+        entity,
+        TextInputAction::Edit(TextInputEdit::Backspace),
+    );
+    app.update();
+    assert_eq!(contents(&app, entity), "hell");
+}
+
+#[test]
+fn contents_follow_set_text_and_marked_editor_changes() {
+    let mut app = app();
+    let entity = app
+        .world_mut()
+        .spawn((TextInputNode::default(), TextInputContents::default()))
+        .id();
+    app.update();
+
+    app.world_mut()
+        .get_mut::<TextInputBuffer>(entity)
+        .unwrap()
+        .set_text("set");
+    app.update();
+    assert_eq!(contents(&app, entity), "set");
+
+    let mut buffer = app.world_mut().get_mut::<TextInputBuffer>(entity).unwrap();
+    buffer.editor.insert_string(" directly", None);
+    buffer.mark_text_changed();
+    app.update();
+    assert_eq!(contents(&app, entity), " directlyset");
+}
+
+#[test]
+fn contents_are_initialized_from_added_contents() {
+    let mut app = app();
+    let entity = app
+        .world_mut()
+        .spawn((TextInputNode::default(), TextInputContents::new("loaded")))
+        .id();
+    app.update();
+
+    let buffer = app.world().get::<TextInputBuffer>(entity).unwrap();
+    assert_eq!(buffer.get_text(), "loaded");
+    assert_eq!(contents(&app, entity), "loaded");
+}
+
+fn submit(app: &mut App, input: TextInputNode) -> (Entity, Vec<String>) {
+    let entity = app
+        .world_mut()
+        .spawn((input, TextInputContents::new("submitted")))
+        .id();
+    app.world_mut().resource_mut::<InputFocus>().set(entity);
+    app.update();
+
+    queue(app, entity, TextInputAction::Submit);
+    app.update();
+
+    let submitted = app
+        .world_mut()
+        .resource_mut::<Messages<SubmitText>>()
+        .drain()
+        .filter(|submit_text| submit_text.entity == entity)
+        .map(|submit_text| submit_text.text)
+        .collect();
+    (entity, submitted)
+}
+
+#[test]
+fn submit_keeps_focus_by_default() {
+    let mut app = app();
+    let (entity, submitted) = submit(&mut app, TextInputNode::default());
+
+    assert_eq!(submitted, ["submitted"]);
+    assert_eq!(app.world().resource::<InputFocus>().get(), Some(entity));
+}
+
+#[test]
+fn submit_unfocuses_with_unfocus_on_submit() {
+    let mut app = app();
+    let (_, submitted) = submit(
+        &mut app,
+        TextInputNode {
+            unfocus_on_submit: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(submitted, ["submitted"]);
+    assert_eq!(app.world().resource::<InputFocus>().get(), None);
+}
+
+#[test]
+fn submit_clears_text_with_clear_on_submit() {
+    let mut app = app();
+    let (entity, submitted) = submit(
+        &mut app,
+        TextInputNode {
+            clear_on_submit: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(submitted, ["submitted"]);
+    assert_eq!(contents(&app, entity), "");
+}
+
+#[test]
+fn focused_large_document_contents_sync_on_blur() {
+    let mut app = app();
+    let entity = app
+        .world_mut()
+        .spawn((
+            TextInputNode::default(),
+            TextInputContents::default(),
+            TextInputLargeDocument::default(),
+        ))
+        .id();
+    app.world_mut().resource_mut::<InputFocus>().set(entity);
+    app.update();
+
+    queue(
+        &mut app,
+        entity,
+        TextInputAction::Edit(TextInputEdit::Paste("document".to_string())),
+    );
+    app.update();
+    assert_eq!(contents(&app, entity), "");
+
+    app.world_mut().resource_mut::<InputFocus>().clear();
+    app.update();
+    assert_eq!(contents(&app, entity), "document");
+}
+
+/// App that also lays out inputs with a loaded font, and releases and restores inputs with `release_on_blur` set
+fn layout_app() -> (App, Handle<Font>) {
+    let mut app = App::new();
+    let font = Font::try_from_bytes(include_bytes!("../assets/fonts/FiraMono-Medium.ttf").to_vec())
+        .unwrap();
+    let mut fonts = Assets::<Font>::default();
+    let font = fonts.add(font);
+    app.insert_resource(fonts)
+        .init_resource::<Assets<Image>>()
+        .init_resource::<Assets<TextureAtlasLayout>>()
+        .init_resource::<HoverMap>()
+        .init_resource::<TextInputPipeline>()
+        .init_resource::<Clipboard>()
+        .init_resource::<InputFocus>()
+        .add_message::<SubmitText>()
+        .add_message::<TextInputLayoutError>()
+        .add_systems(
+            Update,
+            (
+                restore_released_text_inputs,
+                process_text_input_queues,
+                update_text_input_contents,
+                text_input_system,
+                release_idle_text_inputs,
+            )
+                .chain(),
+        );
+    (app, font)
+}
+
+fn released_count(app: &mut App) -> usize {
+    app.world_mut()
+        .query::<&TextInputReleasedText>()
+        .iter(app.world())
+        .count()
+}
+
+fn buffer_count(app: &mut App) -> usize {
+    app.world_mut()
+        .query::<&TextInputBuffer>()
+        .iter(app.world())
+        .count()
+}
+
+#[test]
+fn idle_inputs_release_their_buffers_until_focused_or_edited() {
+    const INPUTS: usize = 100;
+    let (mut app, font) = layout_app();
+    let entities: Vec<Entity> = (0..INPUTS)
+        .map(|i| {
+            app.world_mut()
+                .spawn((
+                    TextInputNode {
+                        release_on_blur: true,
+                        ..Default::default()
+                    },
+                    TextInputContents::new(format!("input {i}")),
+                    TextFont::from(font.clone()),
+                    ComputedNode {
+                        size: Vec2::new(200., 30.),
+                        ..Default::default()
+                    },
+                ))
+                .id()
+        })
+        .collect();
+    for _ in 0..3 {
+        app.update();
+    }
+
+    // Every idle input is drawn from its layout info, without an editor
+    assert_eq!(buffer_count(&mut app), 0);
+    assert_eq!(released_count(&mut app), INPUTS);
+    let [first, second] = [entities[0], entities[1]];
+    assert_eq!(
+        app.world()
+            .get::<TextInputReleasedText>(first)
+            .unwrap()
+            .get(),
+        "input 0"
+    );
+    assert!(
+        !app.world()
+            .get::<TextInputLayoutInfo>(first)
+            .unwrap()
+            .glyphs
+            .is_empty()
+    );
+
+    // Focusing an input restores its buffer with its text
+    app.world_mut().resource_mut::<InputFocus>().set(first);
+    app.update();
+    assert_eq!(buffer_count(&mut app), 1);
+    assert_eq!(
+        app.world()
+            .get::<TextInputBuffer>(first)
+            .unwrap()
+            .get_text(),
+        "input 0"
+    );
+
+    // Queued edits are applied to unfocused released inputs, at the cursor kept while released
+    queue(
+        &mut app,
+        second,
+        TextInputAction::Edit(TextInputEdit::Paste("!".to_string())),
+    );
+    app.update();
+    assert_eq!(contents(&app, second), "!input 1");
+
+    // Once blurred and idle again, the buffers are released
+    app.world_mut().resource_mut::<InputFocus>().clear();
+    for _ in 0..3 {
+        app.update();
+    }
+    assert_eq!(buffer_count(&mut app), 0);
+    assert_eq!(
+        app.world()
+            .get::<TextInputReleasedText>(second)
+            .unwrap()
+            .get(),
+        "!input 1"
+    );
+}